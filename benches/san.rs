@@ -0,0 +1,78 @@
+//! Times writing the SAN of every legal move, the way `/legal_moves` does for each request, and
+//! the whole `/legal_moves` request.
+//!
+//! Run with `cargo bench --bench san`. SAN used to be written by printing the position as a FEN
+//! and reading it back into shakmaty for every move, which is kept here to compare against.
+
+use std::{hint::black_box, time::{Duration, Instant}};
+use lambda_http::{http::Method, Body, Request};
+use serde_json::json;
+use shakmaty::{fen::Fen, san::San, uci::UciMove, CastlingMode, Chess};
+use chess_rest_api_rust_pleco::{position::Position, router::handler};
+
+const POSITIONS: [(&str, &str); 3] = [
+    ("start", "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"),
+    ("kiwipete", "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1"),
+    ("middlegame", "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10"),
+];
+
+/// Each measurement runs for about this long.
+const MEASUREMENT_TIME: Duration = Duration::from_secs(2);
+
+fn main() {
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+
+    println!("{:<12} {:>22} {:>22} {:>22}", "position", "SAN via FEN (µs)", "SAN (µs)", "/legal_moves (µs)");
+
+    for (name, fen) in POSITIONS {
+        let position = Position::from_fen(fen).unwrap();
+        let moves = position.legal_moves();
+
+        let round_trip = time(|| {
+            for &mv in &moves {
+                let fen = position.fen();
+                let chess: Chess = Fen::from_ascii(fen.as_bytes()).unwrap().into_position(CastlingMode::Standard).unwrap();
+                let uci = UciMove::from_ascii(mv.uci().as_bytes()).unwrap().to_move(&chess).unwrap();
+                black_box(San::from_move(&chess, uci).to_string());
+            }
+        });
+
+        let direct = time(|| {
+            for &mv in &moves {
+                black_box(position.san(mv));
+            }
+        });
+
+        let request = time(|| {
+            let request: Request = lambda_http::http::Request::builder()
+                .method(Method::POST)
+                .uri("/legal_moves")
+                .body(Body::from(json!({ "fen": fen }).to_string()))
+                .unwrap();
+
+            black_box(runtime.block_on(handler(request)).unwrap());
+        });
+
+        println!("{:<12} {:>22.1} {:>22.1} {:>22.1}", name, micros(round_trip), micros(direct), micros(request));
+    }
+}
+
+/// The average time of a call to `f`, after a warm up.
+fn time(mut f: impl FnMut()) -> Duration {
+    for _ in 0..100 {
+        f();
+    }
+
+    let start = Instant::now();
+    let mut iterations = 0;
+    while start.elapsed() < MEASUREMENT_TIME {
+        f();
+        iterations += 1;
+    }
+
+    start.elapsed() / iterations
+}
+
+fn micros(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1_000_000.0
+}
\ No newline at end of file
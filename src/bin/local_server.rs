@@ -0,0 +1,51 @@
+//! Serves the API over plain HTTP for local development, without Lambda or cargo lambda.
+//! Requests go through the same router as the Lambda function.
+//!
+//! Listens on 127.0.0.1:3000 by default. Set `HOST` and `PORT` to change this.
+
+use std::{env, net::SocketAddr};
+use http_body_util::{BodyExt, Full};
+use hyper::{body::{Bytes, Incoming}, server::conn::http1, service::service_fn};
+use hyper_util::rt::TokioIo;
+use lambda_http::{Body, Error, Request};
+use tokio::net::TcpListener;
+use chess_rest_api_rust_pleco::router::handler;
+
+const DEFAULT_HOST: &str = "127.0.0.1";
+const DEFAULT_PORT: u16 = 3000;
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    let host = env::var("HOST").unwrap_or_else(|_| DEFAULT_HOST.to_string());
+    let port = match env::var("PORT") {
+        Ok(port) => port.parse::<u16>().map_err(|e| format!("Invalid PORT {}: {}", port, e))?,
+        Err(_) => DEFAULT_PORT,
+    };
+
+    let address: SocketAddr = format!("{}:{}", host, port).parse()?;
+    let listener = TcpListener::bind(address).await?;
+    println!("Listening on http://{}", address);
+
+    loop {
+        let (stream, _) = listener.accept().await?;
+
+        tokio::spawn(async move {
+            let connection = http1::Builder::new().serve_connection(TokioIo::new(stream), service_fn(serve));
+
+            if let Err(e) = connection.await {
+                eprintln!("Connection error: {}", e);
+            }
+        });
+    }
+}
+
+/// Converts between hyper's request and response types and the ones the router works with.
+async fn serve(req: hyper::Request<Incoming>) -> Result<hyper::Response<Full<Bytes>>, Error> {
+    let (parts, body) = req.into_parts();
+    let body = body.collect().await?.to_bytes();
+
+    let response = handler(Request::from_parts(parts, Body::from(body.to_vec()))).await?;
+
+    let (parts, body) = response.into_parts();
+    Ok(hyper::Response::from_parts(parts, Full::new(Bytes::from(body.to_vec()))))
+}
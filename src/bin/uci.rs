@@ -0,0 +1,354 @@
+//! A UCI front end for the bot, so it can be played in chess GUIs and tournament managers
+//! such as cutechess-cli and Arena. Reads commands from stdin and writes replies to stdout.
+
+use std::{
+    io::{self, BufRead},
+    sync::{atomic::{AtomicBool, Ordering}, Arc},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+use chess_rest_api_rust_pleco::{
+    bot::{
+        engine::{Engine, MAX_SEARCH_DEPTH},
+        skill::{Skill, MAX_SKILL_LEVEL},
+        time_manager::{TimeControl, TimeManager},
+        transposition::{TranspositionTable, DEFAULT_TRANSPOSITION_TABLE_MB},
+    },
+    position::{Position, START_FEN},
+    types::{Colour, Score, SearchReport, Variant},
+};
+
+fn main() {
+    let mut uci = Uci::new();
+
+    for line in io::stdin().lock().lines() {
+        let Ok(line) = line else { break };
+
+        if !uci.handle_command(&line) {
+            break;
+        }
+    }
+
+    uci.stop_search();
+}
+
+struct Uci {
+    position: Position,
+    threads: usize,
+    hash_mb: usize,
+    multipv: usize,
+    skill_level: u8,
+    /// Set by the `UCI_Chess960` option, for positions sent after it.
+    variant: Variant,
+    transposition_table: Arc<TranspositionTable>,
+    /// Stop flag of the running search.
+    stop: Arc<AtomicBool>,
+    /// Set while the running search is pondering, cleared on `ponderhit`.
+    pondering: Arc<AtomicBool>,
+    search: Option<JoinHandle<()>>,
+}
+
+/// Limits sent with a `go` command.
+#[derive(Default)]
+struct GoOptions {
+    depth: Option<u8>,
+    nodes: Option<u64>,
+    move_time: Option<u64>,
+    white_time: Option<u64>,
+    black_time: Option<u64>,
+    white_increment: Option<u64>,
+    black_increment: Option<u64>,
+    moves_to_go: Option<u32>,
+    infinite: bool,
+    ponder: bool,
+}
+
+impl Uci {
+    fn new() -> Self {
+        Self {
+            position: Position::start(),
+            threads: 1,
+            hash_mb: DEFAULT_TRANSPOSITION_TABLE_MB,
+            multipv: 1,
+            skill_level: MAX_SKILL_LEVEL,
+            variant: Variant::Standard,
+            transposition_table: Arc::new(TranspositionTable::new(DEFAULT_TRANSPOSITION_TABLE_MB)),
+            stop: Arc::new(AtomicBool::new(false)),
+            pondering: Arc::new(AtomicBool::new(false)),
+            search: None,
+        }
+    }
+
+    /// Handles a single line from the GUI. Returns false once the GUI has asked us to quit.
+    fn handle_command(&mut self, line: &str) -> bool {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+
+        match tokens.first().copied() {
+            Some("uci") => {
+                println!("id name {} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+                println!("id author Billy Jaffray");
+                println!("option name Threads type spin default 1 min 1 max 256");
+                println!("option name Hash type spin default {} min 1 max 4096", DEFAULT_TRANSPOSITION_TABLE_MB);
+                println!("option name MultiPV type spin default 1 min 1 max 256");
+                println!("option name Skill Level type spin default {} min 0 max {}", MAX_SKILL_LEVEL, MAX_SKILL_LEVEL);
+                println!("option name Ponder type check default false");
+                println!("option name UCI_Chess960 type check default false");
+                println!("uciok");
+            }
+            Some("isready") => println!("readyok"),
+            Some("ucinewgame") => {
+                self.stop_search();
+                self.transposition_table.clear();
+                self.position = Position::start();
+            }
+            Some("setoption") => {
+                self.stop_search();
+                self.set_option(&tokens[1..]);
+            }
+            Some("position") => {
+                self.stop_search();
+                self.set_position(&tokens[1..]);
+            }
+            Some("go") => {
+                self.stop_search();
+                self.go(parse_go(&tokens[1..]));
+            }
+            Some("ponderhit") => self.pondering.store(false, Ordering::Relaxed),
+            Some("stop") => self.stop_search(),
+            Some("quit") => return false,
+            _ => {}
+        }
+
+        true
+    }
+
+    fn set_option(&mut self, tokens: &[&str]) {
+        let Some((name, value)) = parse_option(tokens) else {
+            return
+        };
+
+        match name.to_lowercase().as_str() {
+            "threads" => {
+                if let Ok(threads) = value.parse::<usize>() {
+                    self.threads = threads.max(1);
+                }
+            }
+            "hash" => {
+                if let Ok(hash_mb) = value.parse::<usize>() {
+                    self.hash_mb = hash_mb.max(1);
+                    self.transposition_table = Arc::new(TranspositionTable::new(self.hash_mb));
+                }
+            }
+            "multipv" => {
+                if let Ok(multipv) = value.parse::<usize>() {
+                    self.multipv = multipv.max(1);
+                }
+            }
+            "skill level" => {
+                if let Ok(skill_level) = value.parse::<u8>() {
+                    self.skill_level = skill_level.min(MAX_SKILL_LEVEL);
+                }
+            }
+            // Pondering is driven by the GUI sending go ponder, there is nothing to set up.
+            "ponder" => {}
+            "uci_chess960" => {
+                self.variant = if value == "true" { Variant::Chess960 } else { Variant::Standard };
+            }
+            _ => println!("info string unknown option {}", name),
+        }
+    }
+
+    /// Parses `startpos|fen <fen> [moves <move>...]`.
+    fn set_position(&mut self, tokens: &[&str]) {
+        let moves_index = tokens.iter().position(|token| *token == "moves").unwrap_or(tokens.len());
+
+        let position = match tokens.first().copied() {
+            Some("startpos") => Position::from_variant_fen(self.variant, START_FEN),
+            Some("fen") => Position::from_variant_fen(self.variant, &tokens[1..moves_index].join(" ")),
+            _ => return,
+        };
+
+        let mut position = match position {
+            Ok(position) => position,
+            Err(e) => {
+                println!("info string invalid fen: {}", e.message);
+                return
+            }
+        };
+
+        for uci_move in tokens.iter().skip(moves_index + 1) {
+            match position.uci_move(uci_move) {
+                Some(mv) => position.play(mv),
+                None => {
+                    println!("info string illegal move {}", uci_move);
+                    break;
+                }
+            }
+        }
+
+        self.position = position;
+    }
+
+    fn go(&mut self, options: GoOptions) {
+        let stop = Arc::new(AtomicBool::new(false));
+        self.stop = stop.clone();
+
+        let pondering = Arc::new(AtomicBool::new(options.ponder));
+        self.pondering = pondering.clone();
+
+        let mut engine = Engine::new(self.position.clone())
+            .with_threads(self.threads)
+            .with_multipv(self.multipv)
+            .with_skill(Skill::new(self.skill_level))
+            .with_depth(options.depth.unwrap_or(MAX_SEARCH_DEPTH))
+            .with_transposition_table(self.transposition_table.clone())
+            .with_stop(stop.clone())
+            .with_pondering(pondering)
+            .with_reporter(Box::new(print_info));
+
+        if let Some(nodes) = options.nodes {
+            engine = engine.with_node_limit(nodes);
+        }
+
+        if let Some(time_manager) = self.time_manager(&options) {
+            engine = engine.with_time_manager(time_manager);
+        }
+
+        let infinite = options.infinite;
+
+        self.search = Some(thread::spawn(move || {
+            let best_move = engine.best_move();
+
+            // The GUI expects no bestmove from an infinite search until it sends stop.
+            while infinite && !stop.load(Ordering::Relaxed) {
+                thread::sleep(Duration::from_millis(5));
+            }
+
+            match best_move {
+                Some(best_move) => {
+                    // Only suggest a reply to ponder on if the move played is the one the line starts with.
+                    let ponder_move = best_move.lines.first()
+                        .filter(|line| line.pv[0] == best_move.chosen_move)
+                        .and_then(|line| line.pv.get(1));
+
+                    match ponder_move {
+                        Some(ponder_move) => println!("bestmove {} ponder {}", best_move.chosen_move, ponder_move),
+                        None => println!("bestmove {}", best_move.chosen_move),
+                    }
+                }
+                None => println!("bestmove 0000"),
+            }
+        }));
+    }
+
+    /// Returns `None` if only depth, nodes or `stop` end the search.
+    fn time_manager(&self, options: &GoOptions) -> Option<TimeManager> {
+        if options.infinite {
+            return None
+        }
+
+        if let Some(move_time) = options.move_time {
+            return Some(TimeManager::fixed(Duration::from_millis(move_time)))
+        }
+
+        let (time, increment) = match self.position.turn() {
+            Colour::White => (options.white_time?, options.white_increment.unwrap_or(0)),
+            Colour::Black => (options.black_time?, options.black_increment.unwrap_or(0)),
+        };
+
+        Some(TimeManager::for_clock(&TimeControl {
+            time: Duration::from_millis(time),
+            increment: Duration::from_millis(increment),
+            moves_to_go: options.moves_to_go,
+        }))
+    }
+
+    fn stop_search(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+
+        if let Some(search) = self.search.take() {
+            let _ = search.join();
+        }
+    }
+}
+
+fn parse_go(tokens: &[&str]) -> GoOptions {
+    let mut options = GoOptions::default();
+    let mut tokens = tokens.iter();
+
+    while let Some(token) = tokens.next() {
+        let mut value = || tokens.next().and_then(|value| value.parse::<u64>().ok());
+
+        match *token {
+            "depth" => options.depth = value().map(|depth| depth.min(MAX_SEARCH_DEPTH as u64) as u8),
+            "nodes" => options.nodes = value(),
+            "movetime" => options.move_time = value(),
+            "wtime" => options.white_time = value(),
+            "btime" => options.black_time = value(),
+            "winc" => options.white_increment = value(),
+            "binc" => options.black_increment = value(),
+            "movestogo" => options.moves_to_go = value().map(|moves| moves as u32),
+            "infinite" => options.infinite = true,
+            "ponder" => options.ponder = true,
+            _ => {}
+        }
+    }
+
+    options
+}
+
+/// Parses `name <id> [value <x>]` into the option's name and value. Option names may contain spaces.
+/// Lines that do not start with `name` are ignored.
+fn parse_option(tokens: &[&str]) -> Option<(String, String)> {
+    if tokens.first() != Some(&"name") {
+        return None
+    }
+
+    let value_index = tokens.iter().position(|token| *token == "value").unwrap_or(tokens.len());
+    let name = tokens[1..value_index].join(" ");
+    let value = tokens.get(value_index + 1..).map(|value| value.join(" ")).unwrap_or_default();
+
+    Some((name, value))
+}
+
+fn print_info(report: &SearchReport) {
+    let millis = report.elapsed.as_millis().max(1);
+    let nps = report.nodes as u128 * 1000 / millis;
+
+    for (index, line) in report.lines.iter().enumerate() {
+        let score = match line.score {
+            Score::Centipawns(centipawns) => format!("cp {}", centipawns),
+            Score::Mate(moves) => format!("mate {}", moves),
+        };
+        let pv: Vec<String> = line.pv.iter().map(|mv| mv.uci()).collect();
+
+        println!(
+            "info depth {} multipv {} score {} nodes {} nps {} time {} pv {}",
+            report.depth, index + 1, score, report.nodes, nps, millis, pv.join(" ")
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_option;
+
+    fn option(line: &str) -> Option<(String, String)> {
+        parse_option(&line.split_whitespace().collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn parses_option_names_and_values() {
+        assert_eq!(option("name Skill Level value 5"), Some(("Skill Level".to_string(), "5".to_string())));
+        assert_eq!(option("name Ponder"), Some(("Ponder".to_string(), String::new())));
+        assert_eq!(option("name Hash value"), Some(("Hash".to_string(), String::new())));
+    }
+
+    #[test]
+    fn ignores_options_without_a_name() {
+        assert_eq!(option(""), None);
+        assert_eq!(option("value x"), None);
+        assert_eq!(option("value"), None);
+        assert_eq!(option("Hash value 32"), None);
+    }
+}
@@ -1,118 +1,841 @@
-use std::{i32};
-use pleco::{BitMove, Board, Player};
-use crate::{bot::heuristics::{heuristic, is_game_over}, types::{BestMove, MoveGenerationData}, utils::uci_to_san};
+use std::{sync::{atomic::{AtomicBool, Ordering}, Arc}, thread, time::{Duration, Instant, SystemTime, UNIX_EPOCH}};
+use crate::{
+    bot::{
+        heuristics::{heuristic, is_game_over, MATE_EVALUATION},
+        skill::{Skill, SkillRng, MAX_SKILL_LEVEL},
+        time_manager::{IterationResult, TimeManager},
+        transposition::{Bound, TranspositionEntry, TranspositionTable, DEFAULT_TRANSPOSITION_TABLE_MB},
+    },
+    position::{Move, Position},
+    types::{BestMove, Colour, MoveGenerationData, Score, SearchLine, SearchReport},
+};
+
+/// Mixed into the position's hash when the bot is playing black.
+/// Evaluations are stored from the bot's point of view, so the same position searched
+/// for the other colour must never share a transposition table entry.
+const BLACK_BOT_KEY: u64 = 0x9E37_79B9_7F4A_7C15;
+
+/// Evaluations at least this large are forced mates.
+const MATE_THRESHOLD: i32 = MATE_EVALUATION - u8::MAX as i32;
+
+/// The deepest the bot can be asked to search.
+pub const MAX_SEARCH_DEPTH: u8 = 64;
+
+/// Called by the main search thread after every completed iteration.
+pub type SearchReporter = Box<dyn Fn(&SearchReport) + Send + Sync>;
 
 pub struct Engine {
-    board: Board,
+    position: Position,
     search_depth: u8,
+    threads: usize,
+    multipv: usize,
+    skill: Skill,
+    seed: u64,
+    node_limit: Option<u64>,
+    time_manager: Option<TimeManager>,
+    stop: Arc<AtomicBool>,
+    pondering: Arc<AtomicBool>,
+    reporter: Option<SearchReporter>,
+    /// Allocated by the first search unless the caller shares one.
+    transposition_table: Option<Arc<TranspositionTable>>,
+    root_moves: Option<Vec<Move>>,
+}
+
+/// The state belonging to a single search thread.
+/// Every thread shares the transposition table and the stop flags, everything else is private.
+struct SearchThread<'a> {
+    transposition_table: &'a TranspositionTable,
+    /// Set by the engine's owner to cancel the search.
+    stop: &'a AtomicBool,
+    /// Set by the main thread once its own search is over, to end the helpers.
+    finished: &'a AtomicBool,
+    bot_colour: Colour,
+    /// Root moves skipped by the search, used to find the second, third... best lines.
+    excluded_root_moves: Vec<Move>,
+    /// The only root moves searched, if restricted.
+    root_moves: Option<&'a [Move]>,
+    nodes: u64,
+    node_limit: Option<u64>,
+    completed_depth: u8,
+    reporter: Option<&'a (dyn Fn(&SearchReport) + Send + Sync)>,
+    start: Instant,
+    time_manager: Option<TimeManager>,
+    /// The root has a single legal move.
+    single_reply: bool,
+    pondering: Option<&'a AtomicBool>,
+    was_pondering: bool,
+    /// The ponderhit has been noticed but the clock not yet read since.
+    ponderhit: bool,
 }
 
 impl Engine {
-    pub fn new(board: Board) -> Self {
+    pub fn new(position: Position) -> Self {
         Self {
-            board,
+            position,
             search_depth: 4,
+            threads: default_threads(),
+            multipv: 1,
+            skill: Skill::new(MAX_SKILL_LEVEL),
+            seed: time_seed(),
+            node_limit: None,
+            time_manager: None,
+            stop: Arc::new(AtomicBool::new(false)),
+            pondering: Arc::new(AtomicBool::new(false)),
+            reporter: None,
+            transposition_table: None,
+            root_moves: None,
         }
     }
 
+    /// Sets how many plies deep the search goes, up to `MAX_SEARCH_DEPTH`.
+    pub fn with_depth(mut self, depth: u8) -> Self {
+        self.search_depth = depth.clamp(1, MAX_SEARCH_DEPTH);
+        self
+    }
+
+    /// Shares a transposition table with other engines, so a long running caller
+    /// keeps what it has learnt between searches.
+    pub fn with_transposition_table(mut self, transposition_table: Arc<TranspositionTable>) -> Self {
+        self.transposition_table = Some(transposition_table);
+        self
+    }
+
+    /// Reports the lines found at every depth while the search is running.
+    pub fn with_reporter(mut self, reporter: SearchReporter) -> Self {
+        self.reporter = Some(reporter);
+        self
+    }
+
+    /// Sets the number of threads used by the Lazy SMP search.
+    /// One thread searches on the calling thread only.
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    /// Sets the number of lines (best moves) to search from the root.
+    pub fn with_multipv(mut self, multipv: usize) -> Self {
+        self.multipv = multipv.max(1);
+        self
+    }
+
+    /// Weakens the bot to the given skill level.
+    pub fn with_skill(mut self, skill: Skill) -> Self {
+        self.skill = skill;
+        self
+    }
+
+    /// Seeds the random choices made by a weakened bot.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Stops the search after roughly this many nodes, keeping the deepest completed iteration.
+    ///
+    /// A node limited search runs on a single thread, so with a fixed seed the result depends only
+    /// on the position and the options, never on timing or the machine it runs on.
+    pub fn with_node_limit(mut self, node_limit: u64) -> Self {
+        self.node_limit = Some(node_limit);
+        self
+    }
+
+    /// Lets the time manager decide when to stop, rather than only the depth.
+    pub fn with_time_manager(mut self, time_manager: TimeManager) -> Self {
+        self.time_manager = Some(time_manager);
+        self
+    }
+
+    /// Shares a stop flag with the caller. Once the flag is set the search ends as soon as possible
+    /// and returns the best move found so far, marked as truncated.
+    pub fn with_stop(mut self, stop: Arc<AtomicBool>) -> Self {
+        self.stop = stop;
+        self
+    }
+
+    /// Searches in ponder mode while the flag is set: the position is the one after the reply
+    /// the bot expects, and the search ignores its time limits until the caller clears the flag on
+    /// a ponderhit. The search then carries on as normal, keeping the depth it has already reached,
+    /// and the time spent pondering counts towards the time manager's limits.
+    pub fn with_pondering(mut self, pondering: Arc<AtomicBool>) -> Self {
+        self.pondering = pondering;
+        self
+    }
+
+    /// Only searches these moves from the root, like UCI's `searchmoves`, so the lines found
+    /// score them from the same point of view as an unrestricted search. Illegal moves are ignored.
+    pub fn with_root_moves(mut self, root_moves: Vec<Move>) -> Self {
+        self.root_moves = Some(root_moves);
+        self
+    }
+
     pub fn best_move(&mut self) -> Option<BestMove> {
-        if is_game_over(&self.board) {
+        if is_game_over(&self.position) {
             return None
         }
 
-        let mut current_board = self.board.clone();
-        let bot_colour = self.board.turn();
-        let node_height = self.search_depth;
-        let root_height = self.search_depth;
-        let alpha = i32::MIN;
-        let beta = i32::MAX;
+        let legal_moves: Vec<Move> = self.position.legal_moves().into_iter()
+            .filter(|mv| self.root_moves.as_ref().is_none_or(|root_moves| root_moves.contains(mv)))
+            .collect();
+
+        if legal_moves.is_empty() {
+            return None
+        }
+
+        let bot_colour = self.position.turn();
+        let search_depth = self.skill.search_depth(self.search_depth);
+        let multipv = self.skill.multipv(self.multipv);
+        let node_limit = self.node_limit;
+        let time_manager = self.time_manager.clone();
+        // A restricted root still needs its moves scored, so only a forced move is not worth thinking about.
+        let single_reply = self.position.legal_moves().len() == 1;
+        let root_moves = self.root_moves.is_some().then_some(legal_moves.as_slice());
+        let transposition_table = Arc::clone(self.transposition_table
+            .get_or_insert_with(|| Arc::new(TranspositionTable::new(DEFAULT_TRANSPOSITION_TABLE_MB))));
+        let transposition_table = transposition_table.as_ref();
+        let reporter = self.reporter.as_deref();
+        let stop = self.stop.as_ref();
+        let pondering = self.pondering.as_ref();
+        let finished = AtomicBool::new(false);
+
+        // Helpers change what the main thread finds in the transposition table depending on how the
+        // threads are scheduled, so a node limited search has to stay on one thread to be reproducible.
+        let threads = if node_limit.is_some() { 1 } else { self.threads };
+
+        // Lazy SMP: every helper searches the same root and only communicates through the shared
+        // transposition table. The helpers fill the table with entries the main thread can reuse,
+        // and once the main thread finishes its search the helpers are told to stop and their results are thrown away.
+        let (mut lines, truncated) = std::thread::scope(|scope| {
+            for helper_id in 1..threads {
+                let mut helper_position = self.position.clone();
+                let finished = &finished;
+
+                scope.spawn(move || {
+                    // Odd helpers start one ply deeper so the threads spread out across depths
+                    // instead of searching the same tree in lockstep.
+                    let start_depth = 1 + (helper_id % 2) as u8;
+                    SearchThread::new(transposition_table, stop, finished, bot_colour)
+                        .with_root_moves(root_moves)
+                        .iterative_deepening(&mut helper_position, start_depth, search_depth + 1, 1);
+                });
+            }
+
+            let mut main_position = self.position.clone();
+            let mut main_thread = SearchThread::new(transposition_table, stop, &finished, bot_colour)
+                .with_root_moves(root_moves)
+                .with_node_limit(node_limit)
+                .with_reporter(reporter)
+                .with_time_manager(time_manager, single_reply)
+                .with_pondering(pondering);
+            let lines = main_thread.iterative_deepening(&mut main_position, 1, search_depth, multipv);
 
-        let move_gen = self.search(&mut current_board, bot_colour, BitMove::null(), node_height, root_height, alpha, beta);
+            // Returning before the ponderhit would have the caller play the move too early.
+            while pondering.load(Ordering::Relaxed) && !stop.load(Ordering::Relaxed) {
+                thread::sleep(Duration::from_millis(1));
+            }
+            let truncated = main_thread.completed_depth < search_depth && stop.load(Ordering::Relaxed);
+
+            finished.store(true, Ordering::Relaxed);
+            (lines, truncated)
+        });
+
+        // Stopped before a single root move was searched, so play anything legal rather than nothing.
+        if lines.is_empty() {
+            lines.push(SearchLine {
+                score: score(heuristic(&self.position, bot_colour)),
+                depth: 0,
+                pv: vec![legal_moves[0]],
+            });
+        }
 
-        let uci_move: String = move_gen.bit_move.stringify();
-        let san_move: String = uci_to_san(&current_board.fen(), &uci_move);
+        // A weakened bot may not play its best line, but the lines it reports are still ordered best first.
+        let chosen_move = lines[self.skill.pick_line(&lines, &mut SkillRng::new(self.seed))].pv[0];
+        lines.truncate(self.multipv);
 
-        current_board.apply_move(move_gen.bit_move);
+        let mut resulting_position = self.position.clone();
+        resulting_position.play(chosen_move);
 
-        Some(BestMove { 
-            uci_move,
-            san_move,
-            resulting_board: current_board,
+        Some(BestMove {
+            chosen_move,
+            resulting_position,
+            lines,
+            seed: self.seed,
+            truncated,
         })
     }
+}
+
+impl<'a> SearchThread<'a> {
+    fn new(transposition_table: &'a TranspositionTable, stop: &'a AtomicBool, finished: &'a AtomicBool, bot_colour: Colour) -> Self {
+        Self {
+            transposition_table,
+            stop,
+            finished,
+            bot_colour,
+            excluded_root_moves: Vec::new(),
+            root_moves: None,
+            nodes: 0,
+            node_limit: None,
+            completed_depth: 0,
+            reporter: None,
+            start: Instant::now(),
+            time_manager: None,
+            single_reply: false,
+            pondering: None,
+            was_pondering: false,
+            ponderhit: false,
+        }
+    }
+
+    fn with_root_moves(mut self, root_moves: Option<&'a [Move]>) -> Self {
+        self.root_moves = root_moves;
+        self
+    }
+
+    fn with_pondering(mut self, pondering: &'a AtomicBool) -> Self {
+        self.was_pondering = pondering.load(Ordering::Relaxed);
+        self.pondering = Some(pondering);
+        self
+    }
+
+    fn with_time_manager(mut self, time_manager: Option<TimeManager>, single_reply: bool) -> Self {
+        self.time_manager = time_manager;
+        self.single_reply = single_reply;
+        self
+    }
+
+    fn with_node_limit(mut self, node_limit: Option<u64>) -> Self {
+        self.node_limit = node_limit;
+        self
+    }
+
+    fn with_reporter(mut self, reporter: Option<&'a (dyn Fn(&SearchReport) + Send + Sync)>) -> Self {
+        self.reporter = reporter;
+        self
+    }
+
+    fn out_of_nodes(&self) -> bool {
+        self.node_limit.is_some_and(|node_limit| self.nodes >= node_limit)
+    }
+
+    /// Reading the clock is slow compared to searching a node, so it is only read every 1024 nodes
+    /// and straight after a ponderhit, when the pondering may already have used up the time for the move.
+    fn out_of_time(&mut self) -> bool {
+        if self.time_manager.is_none() || self.is_pondering() {
+            return false
+        }
+
+        let ponderhit = std::mem::take(&mut self.ponderhit);
+
+        (ponderhit || self.nodes.is_multiple_of(1024))
+            && self.time_manager.as_ref().is_some_and(|time_manager| time_manager.hard_limit_reached())
+    }
+
+    /// Also notices the ponderhit, so the next node reads the clock.
+    fn is_pondering(&mut self) -> bool {
+        let pondering = self.pondering.is_some_and(|pondering| pondering.load(Ordering::Relaxed));
+
+        if self.was_pondering && !pondering {
+            self.was_pondering = false;
+            self.ponderhit = true;
+        }
+
+        pondering
+    }
+
+    fn stopped(&self) -> bool {
+        self.stop.load(Ordering::Relaxed) || self.finished.load(Ordering::Relaxed)
+    }
+
+    /// Searches to increasing depths, returning the lines of the deepest completed search, best first.
+    /// Each iteration leaves its best moves in the transposition table, which orders the next one.
+    ///
+    /// Within an iteration, each line after the first is found by searching the root again
+    /// with the best moves of the earlier lines excluded.
+    fn iterative_deepening(&mut self, position: &mut Position, start_depth: u8, max_depth: u8, multipv: usize) -> Vec<SearchLine> {
+        let mut best_lines = Vec::new();
+
+        'deepening: for depth in start_depth..=max_depth {
+            let mut lines: Vec<SearchLine> = Vec::new();
+            let mut best_evaluation = 0;
+            self.excluded_root_moves.clear();
+
+            while lines.len() < multipv {
+                let mut pv = Vec::new();
+                let move_gen = self.search(position, depth, 0, i32::MIN, i32::MAX, &mut pv);
+
+                if self.stopped() {
+                    // Nothing has completed yet, so keep what the interrupted iteration had found.
+                    if best_lines.is_empty() {
+                        if !move_gen.mv.is_null() {
+                            lines.push(SearchLine {
+                                score: score(move_gen.evaluation),
+                                depth,
+                                pv,
+                            });
+                        }
+
+                        best_lines = lines;
+                    }
+
+                    break 'deepening;
+                }
+
+                // Every root move has already been given a line.
+                if move_gen.mv.is_null() {
+                    break;
+                }
+
+                if lines.is_empty() {
+                    best_evaluation = move_gen.evaluation;
+                }
+
+                self.excluded_root_moves.push(move_gen.mv);
+                lines.push(SearchLine {
+                    score: score(move_gen.evaluation),
+                    depth,
+                    pv,
+                });
+            }
+
+            // Lines found later can still score higher when the search picks up deeper results from the
+            // transposition table, so put them back in order.
+            lines.sort_by_key(|line| std::cmp::Reverse(line.score));
+
+            if let Some(reporter) = self.reporter {
+                reporter(&SearchReport {
+                    depth,
+                    nodes: self.nodes,
+                    elapsed: self.start.elapsed(),
+                    lines: &lines,
+                });
+            }
+
+            let best_move = lines[0].pv[0];
+            best_lines = lines;
+            self.completed_depth = depth;
+
+            if self.is_pondering() {
+                continue;
+            }
+
+            if let Some(time_manager) = self.time_manager.as_mut() {
+                let result = IterationResult {
+                    best_move: best_move.to_bits(),
+                    score: best_evaluation,
+                    single_reply: self.single_reply,
+                };
+
+                if !time_manager.continue_search(&result) {
+                    break;
+                }
+            }
+        }
+
+        best_lines
+    }
+
+    fn key(&self, position: &Position) -> u64 {
+        match self.bot_colour {
+            Colour::White => position.hash(),
+            Colour::Black => position.hash() ^ BLACK_BOT_KEY,
+        }
+    }
+
+    /// Rebuilds the principal variation cut short by a transposition table hit, following the moves
+    /// of exact entries for up to `length` plies. A slot can be overwritten by another position that
+    /// maps to it, so each move is checked to be legal before it is followed.
+    fn table_pv(&self, position: &mut Position, length: u8, pv: &mut Vec<Move>) {
+        let mut played = 0;
+
+        while played < length {
+            let Some(entry) = self.transposition_table.probe(self.key(position)) else {
+                break
+            };
+
+            if entry.bound != Bound::Exact || entry.mv.is_null() || !position.legal_moves().contains(&entry.mv) {
+                break
+            }
+
+            pv.push(entry.mv);
+            position.play(entry.mv);
+            played += 1;
+        }
+
+        for _ in 0..played {
+            position.undo();
+        }
+    }
+
+    /// Alpha-beta search from the bot's point of view.
+    /// The returned `mv` is the best move from `position`, or null at a leaf.
+    /// `pv` is filled with the principal variation starting from that move.
+    fn search(&mut self, position: &mut Position, node_height: u8, ply: u8, mut alpha: i32, mut beta: i32, pv: &mut Vec<Move>) -> MoveGenerationData {
+        pv.clear();
+        self.nodes += 1;
+
+        // The first iteration always runs to completion so there is a move to return.
+        if self.completed_depth > 0 && (self.out_of_nodes() || self.out_of_time()) {
+            self.finished.store(true, Ordering::Relaxed);
+        }
+
+        let key = self.key(position);
+        let mut table_move = Move::null();
+
+        if let Some(entry) = self.transposition_table.probe(key) {
+            table_move = entry.mv;
+
+            // Never return early from the root, we always need a move to play.
+            if ply > 0 && entry.depth >= node_height {
+                let evaluation = evaluation_from_table(entry.evaluation, ply);
+                let usable = match entry.bound {
+                    Bound::Exact => true,
+                    Bound::Lower => evaluation >= beta,
+                    Bound::Upper => evaluation <= alpha,
+                };
+
+                if usable {
+                    // Only an exact entry's move is known to be the best reply, a bound's may not be.
+                    if entry.bound == Bound::Exact {
+                        self.table_pv(position, node_height, pv);
+                    }
+
+                    return MoveGenerationData {
+                        evaluation,
+                        height: node_height,
+                        mv: entry.mv,
+                    }
+                }
+            }
+        }
 
-    fn search(&self, board: &mut Board, bot_colour: Player, move_made: BitMove, node_height: u8, root_height: u8, mut alpha: i32, mut beta: i32) -> MoveGenerationData {
         if node_height == 0 {
-            MoveGenerationData {
-                evaluation: heuristic(&board, bot_colour),
+            return MoveGenerationData {
+                evaluation: evaluation_from_root(heuristic(position, self.bot_colour), ply),
                 height: 0,
-                bit_move: move_made,
+                mv: Move::null(),
             }
+        }
+
+        let legal_moves = position.legal_moves();
+
+        if legal_moves.is_empty() {
+            return MoveGenerationData {
+                evaluation: evaluation_from_root(heuristic(position, self.bot_colour), ply),
+                height: node_height,
+                mv: Move::null(),
+            }
+        }
+
+        let original_alpha = alpha;
+        let original_beta = beta;
+
+        // If it is the bot's move, we pass up the maximum score, otherwise we pass up the minimum:
+        let maximising = position.turn() == self.bot_colour;
+        let mut value = if maximising {
+            MoveGenerationData::worst_evaluation()
         } else {
-            let legal_moves = board.generate_moves();
+            MoveGenerationData::best_evaluation()
+        };
 
-            if legal_moves.len() == 0 {
-                return MoveGenerationData {
-                    evaluation: heuristic(&board, bot_colour),
-                    height: node_height,
-                    bit_move: move_made,
-                }
+        let mut child_pv = Vec::new();
+
+        for mv in order_moves(position, legal_moves, table_move) {
+            if ply == 0 && (self.excluded_root_moves.contains(&mv) || self.root_moves.is_some_and(|root_moves| !root_moves.contains(&mv))) {
+                continue;
             }
 
-            // If it is the bot's move, we pass up the maximum score, otherwise we pass up the minimum:
-            if bot_colour == board.turn() {  
-                let mut value = MoveGenerationData::worst_evaluation();
-
-                for mv in legal_moves.iter() {
-                    board.apply_move(*mv);
-                    
-                    // We only want to propagate the moves from the original vector of legal_moves.
-                    // If we are at the first iteration, we progagate the move we just made.
-                    // If not, then we propagate our parent's move.
-                    let move_made = if node_height == root_height {*mv} else {move_made};
-
-                    value = std::cmp::max(value, self.search(board, bot_colour, move_made, node_height-1, root_height, alpha, beta));
-                    
-                    if value.evaluation > beta {
-                        board.undo_move();
-                        break;
-                    }
+            position.play(mv);
+            let child = self.search(position, node_height - 1, ply + 1, alpha, beta, &mut child_pv);
+            position.undo();
 
-                    alpha = i32::max(alpha,value.evaluation);
+            // A stopped search returns garbage, so make sure none of it reaches the table.
+            if self.stopped() {
+                return value
+            }
 
-                    board.undo_move();
+            if maximising {
+                if child.evaluation > value.evaluation {
+                    value = MoveGenerationData { evaluation: child.evaluation, height: node_height, mv };
+                    set_pv(pv, mv, &child_pv);
                 }
-                return value
+
+                if value.evaluation >= beta {
+                    break;
+                }
+
+                alpha = i32::max(alpha, value.evaluation);
             } else {
-                let mut value = MoveGenerationData::best_evaluation();
+                if child.evaluation < value.evaluation {
+                    value = MoveGenerationData { evaluation: child.evaluation, height: node_height, mv };
+                    set_pv(pv, mv, &child_pv);
+                }
+
+                if value.evaluation <= alpha {
+                    break;
+                }
 
-                for mv in legal_moves.iter() {
-                    board.apply_move(*mv);
+                beta = i32::min(beta, value.evaluation);
+            }
+        }
 
-                    // We only want to propagate the moves from the original vector of legal_moves.
-                    // If we are at the first iteration, we progagate the move we just made.
-                    // If not, then we propagate our parent's move.
-                    let move_made = if node_height == root_height {*mv} else {move_made};
-                    
-                    value = std::cmp::min(value, self.search(board, bot_colour, move_made, node_height-1, root_height, alpha, beta));
+        // A root searched with moves excluded does not hold the true value of the position.
+        if ply == 0 && (!self.excluded_root_moves.is_empty() || self.root_moves.is_some()) {
+            return value
+        }
 
-                    if value.evaluation < alpha {
-                        board.undo_move();
-                        break;
-                    }
+        let bound = if value.evaluation <= original_alpha {
+            Bound::Upper
+        } else if value.evaluation >= original_beta {
+            Bound::Lower
+        } else {
+            Bound::Exact
+        };
 
-                    beta = i32::min(beta,value.evaluation);
+        self.transposition_table.store(key, TranspositionEntry {
+            evaluation: evaluation_to_table(value.evaluation, ply),
+            depth: node_height,
+            bound,
+            mv: value.mv,
+        });
 
-                    board.undo_move();
-                }
-                return value
+        value
+    }
+}
+
+fn time_seed() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_nanos() as u64)
+        .unwrap_or(0)
+}
+
+pub(crate) fn default_threads() -> usize {
+    std::thread::available_parallelism()
+        .map(|threads| threads.get())
+        .unwrap_or(1)
+}
+
+/// Searches the transposition table move first, then captures, then everything else.
+fn order_moves(position: &Position, mut moves: Vec<Move>, table_move: Move) -> Vec<Move> {
+    moves.sort_by_key(|mv| {
+        if *mv == table_move {
+            0
+        } else if position.is_capture(*mv) {
+            1
+        } else {
+            2
+        }
+    });
+
+    moves
+}
+
+fn set_pv(pv: &mut Vec<Move>, mv: Move, child_pv: &[Move]) {
+    pv.clear();
+    pv.push(mv);
+    pv.extend_from_slice(child_pv);
+}
+
+/// Converts a root evaluation into a score, counting mates in moves rather than plies.
+fn score(evaluation: i32) -> Score {
+    if evaluation >= MATE_THRESHOLD {
+        Score::Mate((MATE_EVALUATION - evaluation + 1) / 2)
+    } else if evaluation <= -MATE_THRESHOLD {
+        Score::Mate(-(MATE_EVALUATION + evaluation + 1) / 2)
+    } else {
+        Score::Centipawns(evaluation)
+    }
+}
+
+/// Mates are scored by their distance from the root so the bot prefers the quickest win and the slowest loss.
+fn evaluation_from_root(evaluation: i32, ply: u8) -> i32 {
+    if evaluation == MATE_EVALUATION {
+        evaluation - ply as i32
+    } else if evaluation == -MATE_EVALUATION {
+        evaluation + ply as i32
+    } else {
+        evaluation
+    }
+}
+
+/// Mates are stored relative to the node rather than the root, so they stay correct
+/// when the position is reached again at a different distance from the root.
+fn evaluation_to_table(evaluation: i32, ply: u8) -> i32 {
+    if evaluation >= MATE_THRESHOLD {
+        evaluation + ply as i32
+    } else if evaluation <= -MATE_THRESHOLD {
+        evaluation - ply as i32
+    } else {
+        evaluation
+    }
+}
+
+fn evaluation_from_table(evaluation: i32, ply: u8) -> i32 {
+    if evaluation >= MATE_THRESHOLD {
+        evaluation - ply as i32
+    } else if evaluation <= -MATE_THRESHOLD {
+        evaluation + ply as i32
+    } else {
+        evaluation
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::{atomic::{AtomicBool, Ordering}, Arc}, time::Duration};
+    use crate::{
+        bot::{skill::Skill, time_manager::TimeManager, transposition::TranspositionTable},
+        position::Position,
+        types::{Colour, Score, Variant},
+    };
+    use super::{Engine, SearchThread};
+
+    /// FEN, node limit, seed, skill level, expected move and expected principal variation.
+    type RegressionCase = (&'static str, u64, u64, u8, &'static str, &'static [&'static str]);
+
+    /// Searches `fen` with a node limit and seed, returning the move played and the principal variation.
+    fn search(fen: &str, nodes: u64, seed: u64, skill_level: u8) -> (String, Vec<String>) {
+        let best_move = Engine::new(Position::from_fen(fen).unwrap())
+            .with_threads(4)
+            .with_node_limit(nodes)
+            .with_seed(seed)
+            .with_skill(Skill::new(skill_level))
+            .best_move()
+            .unwrap();
+
+        let pv = best_move.lines[0].pv.iter().map(|mv| mv.uci()).collect();
+        (best_move.chosen_move.uci(), pv)
+    }
+
+    #[test]
+    fn node_limited_search_is_reproducible() {
+        let expected: [RegressionCase; 7] = [
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", 2_000, 0, 20, "b1c3", &["b1c3", "b8c6", "g1f3"]),
+            ("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3", 5_000, 0, 20, "f1b5", &["f1b5", "c6b4", "b5d7"]),
+            ("r1bqkbnr/pppp1ppp/2n5/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 2 3", 5_000, 0, 20, "h5f7", &["h5f7"]),
+            ("6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - 0 1", 10_000, 0, 20, "a1a8", &["a1a8"]),
+            ("8/8/4k3/8/2p5/8/B2PK3/8 w - - 0 1", 3_000, 0, 20, "a2c4", &["a2c4", "e6e5", "e2d3", "e5f4"]),
+            // A weakened bot reports its best line but plays whichever move the seed picks.
+            ("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3", 5_000, 42, 5, "d2d3", &["b1c3", "g8f6"]),
+            ("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3", 5_000, 7, 5, "b1c3", &["b1c3", "g8f6"]),
+        ];
+
+        for (fen, nodes, seed, skill_level, expected_move, expected_pv) in expected {
+            let (uci_move, pv) = search(fen, nodes, seed, skill_level);
+            assert_eq!(uci_move, expected_move, "move for {}", fen);
+            assert_eq!(pv, expected_pv, "pv for {}", fen);
+        }
+    }
+
+    #[test]
+    fn stopped_search_still_returns_a_legal_move() {
+        let position = Position::from_fen("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3").unwrap();
+        let legal_moves = position.legal_moves();
+
+        let best_move = Engine::new(position)
+            .with_stop(Arc::new(AtomicBool::new(true)))
+            .best_move()
+            .unwrap();
+
+        assert!(best_move.truncated);
+        assert!(legal_moves.contains(&best_move.chosen_move));
+    }
+
+    #[test]
+    fn node_limited_search_ignores_thread_count() {
+        let fen = "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3";
+
+        let lines = |threads: usize| {
+            Engine::new(Position::from_fen(fen).unwrap())
+                .with_threads(threads)
+                .with_node_limit(3_000)
+                .with_multipv(3)
+                .best_move()
+                .unwrap()
+                .lines
+                .into_iter()
+                .map(|line| (line.score, line.depth, line.pv))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(lines(1), lines(8));
+    }
+
+    #[test]
+    fn multipv_lines_are_full_legal_continuations() {
+        let position = Position::start();
+
+        let best_move = Engine::new(position.clone())
+            .with_threads(1)
+            .with_depth(4)
+            .with_multipv(5)
+            .best_move()
+            .unwrap();
+
+        for line in best_move.lines {
+            // Transposition table hits below the root must not cut the line short.
+            assert_eq!(line.pv.len(), 4, "pv {:?}", line.pv);
+
+            let mut line_position = position.clone();
+            for mv in line.pv {
+                assert!(line_position.legal_moves().contains(&mv));
+                line_position.play(mv);
             }
         }
     }
 
-    // fn apply_heuristic(&self, bit_move: BitMove, bot_colour: Player) -> i32 {
-    //     let mut cloned_board = self.board.clone();
-    //     cloned_board.apply_move(bit_move);
-    //     heuristic(&cloned_board, bot_colour)
-    // }
+    #[test]
+    fn ponderhit_counts_the_time_spent_pondering() {
+        let (stop, finished, pondering) = (AtomicBool::new(false), AtomicBool::new(false), AtomicBool::new(true));
+        let transposition_table = TranspositionTable::new(1);
+
+        // A hard limit of 10ms once the overhead is kept back.
+        let mut search = SearchThread::new(&transposition_table, &stop, &finished, Colour::White)
+            .with_time_manager(Some(TimeManager::fixed(Duration::from_millis(60))), false)
+            .with_pondering(&pondering);
+        search.nodes = 1;
+        std::thread::sleep(Duration::from_millis(20));
+
+        // Pondering ignores the limits, however long it has gone on.
+        assert!(!search.out_of_time());
+
+        // The ponderhit is noticed on the very next node, between clock checks, and the time
+        // already spent pondering has used up the move.
+        pondering.store(false, Ordering::Relaxed);
+        assert!(search.out_of_time());
+    }
+
+    #[test]
+    fn root_moves_restrict_the_search() {
+        let position = Position::from_fen("r1bqkbnr/pppp1ppp/2n5/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 2 3").unwrap();
+        let quiet_move = position.uci_move("a2a3").unwrap();
+
+        let best_move = Engine::new(position)
+            .with_node_limit(5_000)
+            .with_multipv(3)
+            .with_root_moves(vec![quiet_move])
+            .best_move()
+            .unwrap();
+
+        assert_eq!(best_move.chosen_move, quiet_move);
+        assert_eq!(best_move.lines.len(), 1);
+        assert!(matches!(best_move.lines[0].score, Score::Centipawns(_)));
+    }
+
+    #[test]
+    fn searches_chess960_positions() {
+        // Castling with the king already on g1 only moves the rook, and mates.
+        let position = Position::from_variant_fen(Variant::Chess960, "4rkr1/4p1p1/8/8/8/8/8/6KR w H - 0 1").unwrap();
+
+        let best_move = Engine::new(position.clone())
+            .with_node_limit(5_000)
+            .best_move()
+            .unwrap();
+
+        assert_eq!(best_move.chosen_move.uci(), "g1h1");
+        assert_eq!(position.san(best_move.chosen_move), "O-O#");
+        assert_eq!(best_move.lines[0].score, Score::Mate(1));
+        assert!(best_move.resulting_position.is_checkmate());
+    }
 }
\ No newline at end of file
@@ -1,95 +1,120 @@
-use pleco::{ Board, Piece, Player, SQ};
+use crate::{position::{Position, Square}, types::{Colour, PieceKind}};
 
-pub fn is_game_over(board: &Board) -> bool {
-    board.checkmate() || board.stalemate()
+/// Evaluation of a checkmate in the bot's favour. Losing checkmates are `-MATE_EVALUATION`.
+pub const MATE_EVALUATION: i32 = i32::MAX - 1;
+
+/// Checkmate or stalemate. Fifty plies without a capture or pawn move do not end the game.
+pub fn is_game_over(position: &Position) -> bool {
+    position.legal_moves().is_empty()
 }
 
-pub fn heuristic(board: &Board, bot_colour: Player) -> i32 {
-    if board.checkmate() {
-        if board.turn() == bot_colour {
-            return i32::MIN + 1;
+pub fn heuristic(position: &Position, bot_colour: Colour) -> i32 {
+    if position.is_checkmate() {
+        if position.turn() == bot_colour {
+            return -MATE_EVALUATION;
         } else {
-            return i32::MAX - 1;
+            return MATE_EVALUATION;
         }
     }
 
-    if board.stalemate() {
+    if is_game_over(position) {
         return 0
     }
 
-    // Count the pieces on board by traditional evaluation.
-    // This is used to determine if the game is early or end (for king evaluation).
-    // We discard kings from this evaluation.
-    let mut traditional_piece_value = 0;
-
-    for i in 0..64 {
-        match board.piece_at_sq(SQ(i)) {
-            Piece::WhitePawn => traditional_piece_value += 1,
-            Piece::WhiteKnight => traditional_piece_value += 3,
-            Piece::WhiteBishop => traditional_piece_value += 3,
-            Piece::WhiteRook => traditional_piece_value += 5,
-            Piece::WhiteQueen => traditional_piece_value += 9,
-
-            Piece::BlackPawn => traditional_piece_value += 1,
-            Piece::BlackKnight => traditional_piece_value += 3,
-            Piece::BlackBishop => traditional_piece_value += 3,
-            Piece::BlackRook => traditional_piece_value += 5,
-            Piece::BlackQueen => traditional_piece_value += 9,
-
-            _ => continue
-        }
-    }
-
-    // Threshold for the game to be considered endgame.
-    // This is subject to scrutiny / changing.
-    let is_endgame = traditional_piece_value <= 30;
+    let is_endgame = is_endgame(position);
 
     let mut evaluation = 0;
 
-    for i in 0..64 {
-        match board.piece_at_sq(SQ(i)) {
-            Piece::WhitePawn => evaluation += pawn_value(Player::White, bot_colour, i),
-            Piece::WhiteKnight => evaluation += knight_value(Player::White, bot_colour, i),
-            Piece::WhiteBishop => evaluation += bishop_value(Player::White, bot_colour, i),
-            Piece::WhiteRook => evaluation += rook_value(Player::White, bot_colour, i),
-            Piece::WhiteQueen => evaluation += queen_value(Player::White, bot_colour, i),
-            Piece::WhiteKing => {
+    for square in Square::all() {
+        let Some((colour, piece)) = position.piece_at(square) else { continue };
+        let i = square.index();
+
+        match piece {
+            PieceKind::Pawn => evaluation += pawn_value(colour, bot_colour, i),
+            PieceKind::Knight => evaluation += knight_value(colour, bot_colour, i),
+            PieceKind::Bishop => evaluation += bishop_value(colour, bot_colour, i),
+            PieceKind::Rook => evaluation += rook_value(colour, bot_colour, i),
+            PieceKind::Queen => evaluation += queen_value(colour, bot_colour, i),
+            PieceKind::King => {
                 if is_endgame {
-                    evaluation += king_end_value(Player::White, bot_colour, i)
+                    evaluation += king_end_value(colour, bot_colour, i)
                 } else {
-                    evaluation += king_early_value(Player::White, bot_colour, i)
+                    evaluation += king_early_value(colour, bot_colour, i)
                 }
             },
+        }
+    }
+    evaluation
+}
 
-            Piece::BlackPawn => evaluation += pawn_value(Player::Black, bot_colour, i),
-            Piece::BlackKnight => evaluation += knight_value(Player::Black, bot_colour, i),
-            Piece::BlackBishop => evaluation += bishop_value(Player::Black, bot_colour, i),
-            Piece::BlackRook => evaluation += rook_value(Player::Black, bot_colour, i),
-            Piece::BlackQueen => evaluation += queen_value(Player::Black, bot_colour, i),
-            Piece::BlackKing => {
-                if is_endgame {
-                    evaluation += king_end_value(Player::Black, bot_colour, i)
-                } else {
-                    evaluation += king_early_value(Player::Black, bot_colour, i)
-                }
-            },
+/// The material term of the evaluation for a piece.
+pub fn piece_value(piece: PieceKind) -> i32 {
+    match piece {
+        PieceKind::Pawn => 100,
+        PieceKind::Knight => 320,
+        PieceKind::Bishop => 330,
+        PieceKind::Rook => 500,
+        PieceKind::Queen => 900,
+        PieceKind::King => 20000,
+    }
+}
+
+/// The piece-square term of the evaluation for the piece on `square`, read from its own colour's table.
+pub fn piece_square_value(position: &Position, square: Square) -> i32 {
+    let Some((colour, piece)) = position.piece_at(square) else { return 0 };
+
+    let (white_evaluation, black_evaluation) = match piece {
+        PieceKind::Pawn => (&WHITE_PAWN_EVALUATION, &BLACK_PAWN_EVALUATION),
+        PieceKind::Knight => (&WHITE_KNIGHT_EVALUATION, &BLACK_KNIGHT_EVALUATION),
+        PieceKind::Bishop => (&WHITE_BISHOP_EVALUATION, &BLACK_BISHOP_EVALUATION),
+        PieceKind::Rook => (&WHITE_ROOK_EVALUATION, &BLACK_ROOK_EVALUATION),
+        PieceKind::Queen => (&WHITE_QUEEN_EVALUATION, &BLACK_QUEEN_EVALUATION),
+        PieceKind::King if is_endgame(position) => (&WHITE_KING_END_EVALUATION, &BLACK_KING_END_EVALUATION),
+        PieceKind::King => (&WHITE_KING_EARLY_EVALUATION, &BLACK_KING_EARLY_EVALUATION),
+    };
+
+    match colour {
+        Colour::White => white_evaluation[square.index() as usize] as i32,
+        Colour::Black => black_evaluation[square.index() as usize] as i32,
+    }
+}
+
+/// The king's piece-square term of the evaluation. Higher is safer: tucked away behind its pawns
+/// while there is material on the board, and towards the centre in the endgame.
+pub fn king_safety(position: &Position, colour: Colour) -> i32 {
+    piece_square_value(position, position.king_square(colour))
+}
+
+/// Counts the pieces on board by traditional evaluation, to tell whether the game is early or
+/// late (for king evaluation). Kings are discarded from this evaluation.
+fn is_endgame(position: &Position) -> bool {
+    let mut traditional_piece_value = 0;
+
+    for square in Square::all() {
+        match position.piece_at(square) {
+            Some((_, PieceKind::Pawn)) => traditional_piece_value += 1,
+            Some((_, PieceKind::Knight)) => traditional_piece_value += 3,
+            Some((_, PieceKind::Bishop)) => traditional_piece_value += 3,
+            Some((_, PieceKind::Rook)) => traditional_piece_value += 5,
+            Some((_, PieceKind::Queen)) => traditional_piece_value += 9,
 
             _ => continue
         }
     }
-    evaluation
-}
 
-fn pawn_value(piece_colour: Player, bot_colour: Player, square_index: u8) -> i32 {
+    // Threshold for the game to be considered endgame.
+    // This is subject to scrutiny / changing.
+    traditional_piece_value <= 30
+}
 
-    let pawn_value = 100;
-    let pawn_square_evaluation: i32;
+fn pawn_value(piece_colour: Colour, bot_colour: Colour, square_index: u8) -> i32 {
 
-    if bot_colour == Player::Black {
-        pawn_square_evaluation = BLACK_PAWN_EVALUATION[square_index as usize] as i32
+    let pawn_value = piece_value(PieceKind::Pawn);
+    let pawn_square_evaluation = if bot_colour == Colour::Black {
+        BLACK_PAWN_EVALUATION[square_index as usize] as i32
     } else {
-        pawn_square_evaluation = WHITE_PAWN_EVALUATION[square_index as usize] as i32
-    }
+        WHITE_PAWN_EVALUATION[square_index as usize] as i32
+    };
 
     if piece_colour == bot_colour {
         pawn_value + pawn_square_evaluation
@@ -98,16 +123,14 @@ fn pawn_value(piece_colour: Player, bot_colour: Player, square_index: u8) -> i32
     }
 }
 
-fn knight_value(piece_colour: Player, bot_colour: Player, square_index: u8) -> i32 {
-
-    let knight_value = 320;
-    let knight_square_evaluation: i32;
+fn knight_value(piece_colour: Colour, bot_colour: Colour, square_index: u8) -> i32 {
 
-    if bot_colour == Player::Black {
-        knight_square_evaluation = BLACK_KNIGHT_EVALUATION[square_index as usize] as i32
+    let knight_value = piece_value(PieceKind::Knight);
+    let knight_square_evaluation = if bot_colour == Colour::Black {
+        BLACK_KNIGHT_EVALUATION[square_index as usize] as i32
     } else {
-        knight_square_evaluation = WHITE_KNIGHT_EVALUATION[square_index as usize] as i32
-    }
+        WHITE_KNIGHT_EVALUATION[square_index as usize] as i32
+    };
 
     if piece_colour == bot_colour {
         knight_value + knight_square_evaluation
@@ -116,16 +139,14 @@ fn knight_value(piece_colour: Player, bot_colour: Player, square_index: u8) -> i
     }
 }
 
-fn bishop_value(piece_colour: Player, bot_colour: Player, square_index: u8) -> i32 {
-
-    let bishop_value = 330;
-    let bishop_square_evaluation: i32;
+fn bishop_value(piece_colour: Colour, bot_colour: Colour, square_index: u8) -> i32 {
 
-    if bot_colour == Player::Black {
-        bishop_square_evaluation = BLACK_BISHOP_EVALUATION[square_index as usize] as i32
+    let bishop_value = piece_value(PieceKind::Bishop);
+    let bishop_square_evaluation = if bot_colour == Colour::Black {
+        BLACK_BISHOP_EVALUATION[square_index as usize] as i32
     } else {
-        bishop_square_evaluation = WHITE_BISHOP_EVALUATION[square_index as usize] as i32
-    }
+        WHITE_BISHOP_EVALUATION[square_index as usize] as i32
+    };
 
     if piece_colour == bot_colour {
         bishop_value + bishop_square_evaluation
@@ -134,16 +155,14 @@ fn bishop_value(piece_colour: Player, bot_colour: Player, square_index: u8) -> i
     }
 }
 
-fn rook_value(piece_colour: Player, bot_colour: Player, square_index: u8) -> i32 {
+fn rook_value(piece_colour: Colour, bot_colour: Colour, square_index: u8) -> i32 {
 
-    let rook_value = 500;
-    let rook_square_evaluation: i32;
-
-    if bot_colour == Player::Black {
-        rook_square_evaluation = BLACK_ROOK_EVALUATION[square_index as usize] as i32
+    let rook_value = piece_value(PieceKind::Rook);
+    let rook_square_evaluation = if bot_colour == Colour::Black {
+        BLACK_ROOK_EVALUATION[square_index as usize] as i32
     } else {
-        rook_square_evaluation = WHITE_ROOK_EVALUATION[square_index as usize] as i32
-    }
+        WHITE_ROOK_EVALUATION[square_index as usize] as i32
+    };
 
     if piece_colour == bot_colour {
         rook_value + rook_square_evaluation
@@ -152,16 +171,14 @@ fn rook_value(piece_colour: Player, bot_colour: Player, square_index: u8) -> i32
     }
 }
 
-fn queen_value(piece_colour: Player, bot_colour: Player, square_index: u8) -> i32 {
-
-    let queen_value = 900;
-    let queen_square_evaluation: i32;
+fn queen_value(piece_colour: Colour, bot_colour: Colour, square_index: u8) -> i32 {
 
-    if bot_colour == Player::Black {
-        queen_square_evaluation = BLACK_QUEEN_EVALUATION[square_index as usize] as i32
+    let queen_value = piece_value(PieceKind::Queen);
+    let queen_square_evaluation = if bot_colour == Colour::Black {
+        BLACK_QUEEN_EVALUATION[square_index as usize] as i32
     } else {
-        queen_square_evaluation = WHITE_QUEEN_EVALUATION[square_index as usize] as i32
-    }
+        WHITE_QUEEN_EVALUATION[square_index as usize] as i32
+    };
 
     if piece_colour == bot_colour {
         queen_value + queen_square_evaluation
@@ -170,16 +187,14 @@ fn queen_value(piece_colour: Player, bot_colour: Player, square_index: u8) -> i3
     }
 }
 
-fn king_early_value(piece_colour: Player, bot_colour: Player, square_index: u8) -> i32 {
-
-    let king_value = 20000;
-    let king_square_evaluation: i32;
+fn king_early_value(piece_colour: Colour, bot_colour: Colour, square_index: u8) -> i32 {
 
-    if bot_colour == Player::Black {
-        king_square_evaluation = BLACK_KING_EARLY_EVALUATION[square_index as usize] as i32
+    let king_value = piece_value(PieceKind::King);
+    let king_square_evaluation = if bot_colour == Colour::Black {
+        BLACK_KING_EARLY_EVALUATION[square_index as usize] as i32
     } else {
-        king_square_evaluation = WHITE_KING_EARLY_EVALUATION[square_index as usize] as i32
-    }
+        WHITE_KING_EARLY_EVALUATION[square_index as usize] as i32
+    };
 
     if piece_colour == bot_colour {
         king_value + king_square_evaluation
@@ -188,16 +203,14 @@ fn king_early_value(piece_colour: Player, bot_colour: Player, square_index: u8)
     }
 }
 
-fn king_end_value(piece_colour: Player, bot_colour: Player, square_index: u8) -> i32 {
+fn king_end_value(piece_colour: Colour, bot_colour: Colour, square_index: u8) -> i32 {
 
-    let king_value = 20000;
-    let king_square_evaluation: i32;
-
-    if bot_colour == Player::Black {
-        king_square_evaluation = BLACK_KING_END_EVALUATION[square_index as usize] as i32
+    let king_value = piece_value(PieceKind::King);
+    let king_square_evaluation = if bot_colour == Colour::Black {
+        BLACK_KING_END_EVALUATION[square_index as usize] as i32
     } else {
-        king_square_evaluation = WHITE_KING_END_EVALUATION[square_index as usize] as i32
-    }
+        WHITE_KING_END_EVALUATION[square_index as usize] as i32
+    };
 
     if piece_colour == bot_colour {
         king_value + king_square_evaluation
@@ -219,9 +232,9 @@ fn king_end_value(piece_colour: Player, bot_colour: Player, square_index: u8) ->
 //                                      A7,  B7,  C7,  D7,  E7,  F7,  G7,  H7,
 //                                      A8,  B8,  C8,  D8,  E8,  F8,  G8,  H8,
 //
-// The reasoning for this decision is that Pleco indexes square A1 = 0, B1 = 1...
+// The reasoning for this decision is that squares are indexed A1 = 0, B1 = 1...
 // If the board was setup normally, then the square A8 would be indexed with 0.
-// This arrangement allows for Pleco square indexes to be used into the evaluation table.
+// This arrangement allows for square indexes to be used into the evaluation table.
 // This is true for every evaluation. The black evaluation is the reverse of the white evaluation.
 
 static WHITE_PAWN_EVALUATION: [i8; 64] = [ 0,   0,   0,   0,   0,   0,   0,   0,
@@ -1,3 +1,5 @@
 pub mod engine;
-
-mod heuristics;
\ No newline at end of file
+pub mod heuristics;
+pub mod skill;
+pub mod time_manager;
+pub mod transposition;
\ No newline at end of file
@@ -0,0 +1,171 @@
+use crate::types::{Score, SearchLine};
+
+/// Highest skill level. At this level the bot always plays its best move at full depth.
+pub const MAX_SKILL_LEVEL: u8 = 20;
+
+/// Elo ratings mapped onto skill levels 0 and `MAX_SKILL_LEVEL`, with a linear scale between.
+pub const MIN_ELO: u32 = 800;
+pub const MAX_ELO: u32 = 2000;
+
+/// Number of root lines searched at weakened levels, so there is a choice of reasonable moves.
+const SKILL_MULTIPV: usize = 4;
+
+/// Centipawn value mates are mapped to when comparing lines.
+const MATE_CENTIPAWNS: i32 = 100_000;
+
+/// Weakens the bot's play by limiting its depth and occasionally choosing a slightly worse root move.
+#[derive(Debug, Clone, Copy)]
+pub struct Skill {
+    level: u8,
+}
+
+impl Skill {
+    pub fn new(level: u8) -> Self {
+        Self {
+            level: level.min(MAX_SKILL_LEVEL),
+        }
+    }
+
+    pub fn from_elo(elo: u32) -> Self {
+        let elo = elo.clamp(MIN_ELO, MAX_ELO);
+        let level = (elo - MIN_ELO) * MAX_SKILL_LEVEL as u32 / (MAX_ELO - MIN_ELO);
+        Self::new(level as u8)
+    }
+
+    pub fn is_weakened(&self) -> bool {
+        self.level < MAX_SKILL_LEVEL
+    }
+
+    /// Limits the search depth to one ply for every five skill levels.
+    pub fn search_depth(&self, full_depth: u8) -> u8 {
+        if self.is_weakened() {
+            (1 + self.level / 5).min(full_depth)
+        } else {
+            full_depth
+        }
+    }
+
+    pub fn multipv(&self, requested: usize) -> usize {
+        if self.is_weakened() {
+            requested.max(SKILL_MULTIPV)
+        } else {
+            requested
+        }
+    }
+
+    /// Picks which of the searched lines to play, returning its index into `lines`.
+    ///
+    /// Each line gets a random bonus that grows as the skill level drops, scaled by how close the
+    /// lines are to each other. Most of the gap to the best line is also forgiven, so a line
+    /// is chosen roughly on how good it looks rather than how good it is.
+    /// Lines losing more than a level dependent amount are never chosen, which keeps the
+    /// mistakes to the kind a player of that level would make instead of throwing away pieces.
+    pub fn pick_line(&self, lines: &[SearchLine], rng: &mut SkillRng) -> usize {
+        if !self.is_weakened() || lines.len() < 2 {
+            return 0
+        }
+
+        let top_score = centipawns(lines[0].score);
+        let bottom_score = centipawns(lines[lines.len() - 1].score);
+
+        let weakness = 120 - 2 * self.level as i32;
+        let delta = i32::min(top_score - bottom_score, 100);
+        let max_loss = 50 + (MAX_SKILL_LEVEL - self.level) as i32 * 25;
+
+        let mut best_index = 0;
+        let mut max_score = i32::MIN;
+
+        for (index, line) in lines.iter().enumerate() {
+            let score = centipawns(line.score);
+
+            if top_score - score > max_loss {
+                continue;
+            }
+
+            let push = (weakness * (top_score - score) + delta * (rng.next_u64() % weakness as u64) as i32) / 128;
+
+            if score + push >= max_score {
+                max_score = score + push;
+                best_index = index;
+            }
+        }
+
+        best_index
+    }
+}
+
+fn centipawns(score: Score) -> i32 {
+    match score {
+        Score::Centipawns(centipawns) => centipawns,
+        Score::Mate(moves) if moves > 0 => MATE_CENTIPAWNS - moves,
+        Score::Mate(moves) => -MATE_CENTIPAWNS - moves,
+    }
+}
+
+/// Small SplitMix64 generator. Good enough for picking moves, and seedable so a game can be replayed.
+#[derive(Debug, Clone)]
+pub struct SkillRng {
+    state: u64,
+}
+
+impl SkillRng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::types::{Score, SearchLine};
+    use super::{Skill, SkillRng, MAX_ELO, MAX_SKILL_LEVEL, MIN_ELO};
+
+    /// Four lines a few centipawns apart, the kind a weakened bot chooses between.
+    fn lines() -> Vec<SearchLine> {
+        [35, 20, 10, -5].into_iter()
+            .map(|centipawns| SearchLine {
+                score: Score::Centipawns(centipawns),
+                depth: 2,
+                pv: Vec::new(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn elo_maps_onto_skill_levels() {
+        assert_eq!(Skill::from_elo(0).level, 0);
+        assert_eq!(Skill::from_elo(MIN_ELO).level, 0);
+        assert_eq!(Skill::from_elo((MIN_ELO + MAX_ELO) / 2).level, MAX_SKILL_LEVEL / 2);
+        assert_eq!(Skill::from_elo(MAX_ELO).level, MAX_SKILL_LEVEL);
+        assert_eq!(Skill::from_elo(u32::MAX).level, MAX_SKILL_LEVEL);
+    }
+
+    #[test]
+    fn full_strength_always_picks_the_best_line() {
+        let skill = Skill::new(MAX_SKILL_LEVEL);
+
+        for seed in 0..100 {
+            assert_eq!(skill.pick_line(&lines(), &mut SkillRng::new(seed)), 0);
+        }
+    }
+
+    #[test]
+    fn weakened_picks_depend_only_on_the_seed() {
+        let skill = Skill::new(2);
+        let picks = |seed| skill.pick_line(&lines(), &mut SkillRng::new(seed));
+
+        for seed in 0..100 {
+            assert_eq!(picks(seed), picks(seed));
+        }
+
+        // Replaying a game relies on the same seed picking the same lines from one release to the next.
+        assert_eq!((0..8).map(picks).collect::<Vec<_>>(), [0, 1, 1, 0, 0, 0, 3, 2]);
+    }
+}
@@ -0,0 +1,199 @@
+use std::time::{Duration, Instant};
+
+/// Time kept back from the clock for every move, to cover the network and building the response.
+pub const MOVE_OVERHEAD: Duration = Duration::from_millis(50);
+
+/// Moves left in the game assumed when the client does not say.
+const DEFAULT_MOVES_TO_GO: u32 = 30;
+
+/// The hard limit is at most this many times the planned time for the move.
+const MAX_HARD_LIMIT_MULTIPLE: u32 = 4;
+
+/// A score drop of at least this many centipawns between iterations means something has gone wrong.
+const SCORE_DROP_THRESHOLD: i32 = 30;
+
+/// The clock of the side to move.
+#[derive(Debug, Clone, Copy)]
+pub struct TimeControl {
+    pub time: Duration,
+    pub increment: Duration,
+    pub moves_to_go: Option<u32>,
+}
+
+/// Decides how long the bot thinks for each move.
+///
+/// The soft limit is checked between iterations of the search, and is stretched while the bot
+/// is unsure of its best move. The hard limit is checked during the search and is never exceeded.
+#[derive(Debug, Clone)]
+pub struct TimeManager {
+    start: Instant,
+    soft_limit: Duration,
+    hard_limit: Duration,
+    previous_best_move: Option<u16>,
+    previous_score: Option<i32>,
+}
+
+/// What the last completed iteration of the search found.
+pub struct IterationResult {
+    /// The best move packed into 16 bits, see `Move::to_bits`.
+    pub best_move: u16,
+    /// Score of the best move in centipawns, mates included as very large values.
+    pub score: i32,
+    /// The side to move has only one legal move, so there is nothing to think about.
+    pub single_reply: bool,
+}
+
+impl TimeManager {
+    /// Plans the time for a move from the clock of the side to move, starting now.
+    pub fn for_clock(time_control: &TimeControl) -> Self {
+        let moves_to_go = time_control.moves_to_go.unwrap_or(DEFAULT_MOVES_TO_GO).max(1);
+        let available = time_control.time.saturating_sub(MOVE_OVERHEAD);
+
+        let planned = time_control.time / moves_to_go + time_control.increment * 3 / 4;
+        let hard_limit = (planned * MAX_HARD_LIMIT_MULTIPLE).min(available * 4 / 5);
+
+        Self {
+            start: Instant::now(),
+            soft_limit: planned.min(hard_limit),
+            hard_limit,
+            previous_best_move: None,
+            previous_score: None,
+        }
+    }
+
+    /// Thinks for exactly `move_time`, starting now.
+    pub fn fixed(move_time: Duration) -> Self {
+        let move_time = move_time.saturating_sub(MOVE_OVERHEAD);
+
+        Self {
+            start: Instant::now(),
+            soft_limit: move_time,
+            hard_limit: move_time,
+            previous_best_move: None,
+            previous_score: None,
+        }
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    pub fn hard_limit_reached(&self) -> bool {
+        self.elapsed() >= self.hard_limit
+    }
+
+    /// Called after every completed iteration to decide whether another one should be started.
+    pub fn continue_search(&mut self, result: &IterationResult) -> bool {
+        if result.single_reply {
+            return false
+        }
+
+        let mut soft_limit = self.soft_limit;
+
+        // The best move changed, so the last iteration found something the one before missed.
+        if self.previous_best_move.is_some_and(|best_move| best_move != result.best_move) {
+            soft_limit = soft_limit * 3 / 2;
+        }
+
+        // The score fell, so spend longer looking for a way out.
+        if self.previous_score.is_some_and(|score| score.saturating_sub(result.score) >= SCORE_DROP_THRESHOLD) {
+            soft_limit = soft_limit * 13 / 10;
+        }
+
+        self.previous_best_move = Some(result.best_move);
+        self.previous_score = Some(result.score);
+
+        // The next iteration takes several times longer than the last, so only start it
+        // if there is a fair chance of finishing it.
+        self.elapsed() < soft_limit.min(self.hard_limit) / 2
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+    use super::{IterationResult, TimeControl, TimeManager, MOVE_OVERHEAD};
+
+    fn clock(time: u64, increment: u64) -> TimeManager {
+        TimeManager::for_clock(&TimeControl {
+            time: Duration::from_millis(time),
+            increment: Duration::from_millis(increment),
+            moves_to_go: None,
+        })
+    }
+
+    /// Pretends the search started `elapsed` ago.
+    fn started(mut time_manager: TimeManager, elapsed: Duration) -> TimeManager {
+        time_manager.start = Instant::now() - elapsed;
+        time_manager
+    }
+
+    fn iteration(best_move: u16, score: i32) -> IterationResult {
+        IterationResult { best_move, score, single_reply: false }
+    }
+
+    #[test]
+    fn plans_a_share_of_the_clock() {
+        // A minute split over the 30 moves assumed left, with four times that as the hard limit.
+        let time_manager = clock(60_000, 0);
+        assert_eq!(time_manager.soft_limit, Duration::from_secs(2));
+        assert_eq!(time_manager.hard_limit, Duration::from_secs(8));
+
+        // Three quarters of the increment is spent on top.
+        let time_manager = clock(60_000, 1_000);
+        assert_eq!(time_manager.soft_limit, Duration::from_millis(2_750));
+        assert_eq!(time_manager.hard_limit, Duration::from_secs(11));
+    }
+
+    #[test]
+    fn never_plans_more_than_a_low_clock_has() {
+        for time in [0, 10, 50, 100, 500] {
+            let time_manager = clock(time, 0);
+            let available = Duration::from_millis(time).saturating_sub(MOVE_OVERHEAD);
+
+            assert!(time_manager.hard_limit <= available * 4 / 5, "hard limit for {}ms", time);
+            assert!(time_manager.soft_limit <= time_manager.hard_limit, "soft limit for {}ms", time);
+        }
+
+        // With nothing left after the overhead the search stops as soon as it can.
+        assert!(clock(40, 0).hard_limit_reached());
+    }
+
+    #[test]
+    fn extends_the_search_when_unsure() {
+        // Past half the 2 second soft limit, so a settled search stops.
+        let elapsed = Duration::from_millis(1_200);
+
+        let mut settled = clock(60_000, 0);
+        assert!(settled.continue_search(&iteration(1, 0)));
+        assert!(!started(settled, elapsed).continue_search(&iteration(1, 0)));
+
+        // A new best move stretches the soft limit to 3 seconds.
+        let mut changed = clock(60_000, 0);
+        assert!(changed.continue_search(&iteration(1, 0)));
+        assert!(started(changed, elapsed).continue_search(&iteration(2, 0)));
+
+        // A falling score stretches it to 2.6 seconds.
+        let mut dropped = clock(60_000, 0);
+        assert!(dropped.continue_search(&iteration(1, 0)));
+        assert!(started(dropped, elapsed).continue_search(&iteration(1, -50)));
+    }
+
+    #[test]
+    fn stops_at_once_with_a_single_reply() {
+        let mut time_manager = clock(60_000, 0);
+
+        assert!(!time_manager.continue_search(&IterationResult { best_move: 1, score: 0, single_reply: true }));
+    }
+
+    #[test]
+    fn fixed_time_keeps_back_the_overhead() {
+        let time_manager = TimeManager::fixed(Duration::from_secs(1));
+        assert_eq!(time_manager.soft_limit, Duration::from_secs(1) - MOVE_OVERHEAD);
+        assert_eq!(time_manager.hard_limit, Duration::from_secs(1) - MOVE_OVERHEAD);
+        assert!(!time_manager.hard_limit_reached());
+        assert!(started(time_manager, Duration::from_secs(1)).hard_limit_reached());
+
+        assert!(TimeManager::fixed(Duration::from_millis(10)).hard_limit_reached());
+    }
+}
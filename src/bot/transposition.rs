@@ -0,0 +1,115 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use crate::position::Move;
+
+/// Default size of the transposition table in megabytes.
+pub const DEFAULT_TRANSPOSITION_TABLE_MB: usize = 16;
+
+/// How a stored evaluation relates to the true value of the position.
+/// Alpha-beta cutoffs only give us a bound, not the exact score.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bound {
+    Exact,
+    Lower,
+    Upper,
+}
+
+/// A single entry read back out of the table.
+#[derive(Debug, Clone, Copy)]
+pub struct TranspositionEntry {
+    pub evaluation: i32,
+    pub depth: u8,
+    pub bound: Bound,
+    pub mv: Move,
+}
+
+/// One slot of the table. The key is stored XORed with the data so that a slot torn by
+/// two threads writing at the same time fails the key check and is treated as a miss.
+struct Slot {
+    key: AtomicU64,
+    data: AtomicU64,
+}
+
+/// A fixed size, lock-free transposition table that can be shared between search threads.
+pub struct TranspositionTable {
+    slots: Vec<Slot>,
+    mask: usize,
+}
+
+impl TranspositionTable {
+    pub fn new(size_mb: usize) -> Self {
+        let requested_slots = (size_mb.max(1) * 1024 * 1024) / std::mem::size_of::<Slot>();
+
+        // Round down to a power of two so a slot can be found with a mask rather than a modulo.
+        let slot_count = 1usize << (usize::BITS - 1 - requested_slots.leading_zeros());
+
+        let slots = (0..slot_count)
+            .map(|_| Slot { key: AtomicU64::new(0), data: AtomicU64::new(0) })
+            .collect();
+
+        Self {
+            slots,
+            mask: slot_count - 1,
+        }
+    }
+
+    pub fn probe(&self, key: u64) -> Option<TranspositionEntry> {
+        let slot = &self.slots[key as usize & self.mask];
+        let data = slot.data.load(Ordering::Relaxed);
+
+        if data == 0 || slot.key.load(Ordering::Relaxed) ^ data != key {
+            return None
+        }
+
+        Some(unpack(data))
+    }
+
+    /// Always replaces the existing slot. Deeper entries are more valuable, but at the depths
+    /// the bot searches the simpler scheme performs just as well.
+    pub fn store(&self, key: u64, entry: TranspositionEntry) {
+        let slot = &self.slots[key as usize & self.mask];
+        let data = pack(entry);
+
+        slot.key.store(key ^ data, Ordering::Relaxed);
+        slot.data.store(data, Ordering::Relaxed);
+    }
+
+    pub fn clear(&self) {
+        for slot in self.slots.iter() {
+            slot.key.store(0, Ordering::Relaxed);
+            slot.data.store(0, Ordering::Relaxed);
+        }
+    }
+}
+
+// Layout of the packed data:
+// bits  0..32  evaluation
+// bits 32..48  move
+// bits 48..56  depth
+// bits 56..64  bound (always non-zero, so an occupied slot never packs to 0)
+fn pack(entry: TranspositionEntry) -> u64 {
+    let bound: u64 = match entry.bound {
+        Bound::Exact => 1,
+        Bound::Lower => 2,
+        Bound::Upper => 3,
+    };
+
+    (entry.evaluation as u32 as u64)
+        | ((entry.mv.to_bits() as u64) << 32)
+        | ((entry.depth as u64) << 48)
+        | (bound << 56)
+}
+
+fn unpack(data: u64) -> TranspositionEntry {
+    let bound = match data >> 56 {
+        1 => Bound::Exact,
+        2 => Bound::Lower,
+        _ => Bound::Upper,
+    };
+
+    TranspositionEntry {
+        evaluation: data as u32 as i32,
+        depth: (data >> 48) as u8,
+        bound,
+        mv: Move::from_bits((data >> 32) as u16),
+    }
+}
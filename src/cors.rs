@@ -0,0 +1,127 @@
+use std::env;
+use lambda_http::http::{header, HeaderMap, HeaderValue};
+
+/// How long browsers may cache a preflight response when `CORS_MAX_AGE` is not set.
+const DEFAULT_MAX_AGE_SECONDS: u32 = 86400;
+
+/// Which cross-origin requests browsers are allowed to make.
+///
+/// Read from the environment:
+/// - `CORS_ALLOWED_ORIGINS`: comma separated origins, or `*` for any. Defaults to `*`.
+/// - `CORS_ALLOWED_HEADERS`: comma separated request headers. Defaults to `Content-Type`.
+/// - `CORS_MAX_AGE`: seconds a preflight response may be cached for. Defaults to a day.
+#[derive(Debug, Clone)]
+pub struct CorsConfig {
+    /// `None` allows any origin.
+    allowed_origins: Option<Vec<String>>,
+    allowed_headers: String,
+    max_age: u32,
+}
+
+impl CorsConfig {
+    pub fn from_env() -> Self {
+        let allowed_origins = env::var("CORS_ALLOWED_ORIGINS").ok()
+            .filter(|origins| origins.trim() != "*")
+            .map(|origins| origins.split(',').map(|origin| origin.trim().to_string()).collect());
+
+        let allowed_headers = env::var("CORS_ALLOWED_HEADERS").unwrap_or_else(|_| String::from("Content-Type"));
+
+        let max_age = env::var("CORS_MAX_AGE").ok()
+            .and_then(|max_age| max_age.parse().ok())
+            .unwrap_or(DEFAULT_MAX_AGE_SECONDS);
+
+        Self { allowed_origins, allowed_headers, max_age }
+    }
+
+    /// Adds the headers every response to a cross-origin request needs.
+    /// Nothing is added if the request's origin is not allowed, so the browser blocks the response.
+    pub fn apply(&self, request_headers: &HeaderMap, response_headers: &mut HeaderMap) {
+        let Some(allowed_origin) = self.allowed_origin(request_headers) else { return };
+
+        if allowed_origin != "*" {
+            // The header depends on the request, so caches must not share it between origins.
+            response_headers.append(header::VARY, HeaderValue::from_static("Origin"));
+        }
+
+        response_headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, allowed_origin);
+    }
+
+    /// Adds the extra headers a response to a preflight `OPTIONS` request needs.
+    /// `methods` are the methods the path accepts.
+    pub fn apply_preflight(&self, request_headers: &HeaderMap, response_headers: &mut HeaderMap, methods: &str) {
+        if self.allowed_origin(request_headers).is_none() {
+            return
+        }
+
+        if let Ok(methods) = HeaderValue::from_str(methods) {
+            response_headers.insert(header::ACCESS_CONTROL_ALLOW_METHODS, methods);
+        }
+
+        if let Ok(allowed_headers) = HeaderValue::from_str(&self.allowed_headers) {
+            response_headers.insert(header::ACCESS_CONTROL_ALLOW_HEADERS, allowed_headers);
+        }
+
+        response_headers.insert(header::ACCESS_CONTROL_MAX_AGE, HeaderValue::from(self.max_age));
+    }
+
+    fn allowed_origin(&self, request_headers: &HeaderMap) -> Option<HeaderValue> {
+        match &self.allowed_origins {
+            None => Some(HeaderValue::from_static("*")),
+            Some(allowed_origins) => {
+                let origin = request_headers.get(header::ORIGIN)?;
+                let origin_str = origin.to_str().ok()?;
+
+                allowed_origins.iter()
+                    .any(|allowed_origin| allowed_origin == origin_str)
+                    .then(|| origin.clone())
+            }
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use lambda_http::http::{header, HeaderMap};
+    use super::CorsConfig;
+
+    fn only(origin: &str) -> CorsConfig {
+        CorsConfig {
+            allowed_origins: Some(vec![origin.to_string()]),
+            allowed_headers: String::from("Content-Type, Authorization"),
+            max_age: 600,
+        }
+    }
+
+    fn from(origin: &str) -> HeaderMap {
+        let mut request_headers = HeaderMap::new();
+        request_headers.insert(header::ORIGIN, origin.parse().unwrap());
+        request_headers
+    }
+
+    #[test]
+    fn echoes_an_allowed_origin() {
+        let cors = only("https://app.example.com");
+        let mut response_headers = HeaderMap::new();
+
+        cors.apply(&from("https://app.example.com"), &mut response_headers);
+        cors.apply_preflight(&from("https://app.example.com"), &mut response_headers, "POST, OPTIONS");
+
+        assert_eq!(response_headers[header::ACCESS_CONTROL_ALLOW_ORIGIN], "https://app.example.com");
+        assert_eq!(response_headers[header::VARY], "Origin");
+        assert_eq!(response_headers[header::ACCESS_CONTROL_ALLOW_METHODS], "POST, OPTIONS");
+        assert_eq!(response_headers[header::ACCESS_CONTROL_ALLOW_HEADERS], "Content-Type, Authorization");
+        assert_eq!(response_headers[header::ACCESS_CONTROL_MAX_AGE], "600");
+    }
+
+    #[test]
+    fn adds_nothing_for_another_origin() {
+        let cors = only("https://app.example.com");
+
+        for request_headers in [from("https://evil.example.com"), HeaderMap::new()] {
+            let mut response_headers = HeaderMap::new();
+            cors.apply(&request_headers, &mut response_headers);
+            cors.apply_preflight(&request_headers, &mut response_headers, "POST, OPTIONS");
+
+            assert!(response_headers.is_empty(), "{:?}", response_headers);
+        }
+    }
+}
@@ -0,0 +1,25 @@
+use crate::{
+    position::{Position, START_FEN},
+    types::{ApplyMoveInput, ErrorCode, ResponseError, ResultingGameState},
+    utils::resulting_game_state,
+};
+
+pub async fn apply_move(input: ApplyMoveInput) -> Result<ResultingGameState, ResponseError> {
+    let mut position = Position::from_variant_fen(input.variant, input.fen.as_deref().unwrap_or(START_FEN))?;
+
+    for (ply, notation) in input.history.unwrap_or_default().iter().enumerate() {
+        let mv = position.parse_move(notation).map_err(|e| match e.code {
+            ErrorCode::IllegalMove => e.with_details(serde_json::json!({ "move": notation, "history_index": ply })),
+            _ => e,
+        })?;
+        position.play(mv);
+    }
+
+    if position.game_over().is_some() {
+        return Err(ResponseError::new(ErrorCode::NoLegalMoves, "The game is over, there are no legal moves."))
+    }
+
+    let mv = position.parse_move(&input.chess_move)?;
+
+    resulting_game_state(&mut position, mv)
+}
\ No newline at end of file
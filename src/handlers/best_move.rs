@@ -1,30 +1,143 @@
-use pleco::Board;
-use crate::{bot::engine::Engine, types::{BestMoveResponse, ResponseError}, utils::{game_over, get_resulting_game_states}};
+use std::{sync::{atomic::{AtomicBool, Ordering}, Arc}, time::{Duration, SystemTime}};
+use tokio::task::JoinHandle;
+use crate::{bot::{engine::{Engine, MAX_SEARCH_DEPTH}, skill::{Skill, MAX_SKILL_LEVEL}, time_manager::{TimeControl, TimeManager}}, position::Position, types::{BestMoveInput, BestMoveResponse, CandidateMove, Colour, ErrorCode, ResponseError}, utils::get_resulting_game_states};
 
-pub async fn best_move(fen: String) -> Result<BestMoveResponse, ResponseError> {
-    let board = Board::from_fen(&fen)
-        .map_err(|e| ResponseError { error: format!("{:?}",e) })?;
+/// The most lines a single request can ask for. Each extra line is another search of the root.
+const MAX_MULTIPV: usize = 10;
 
-    let mut engine = Engine::new(board);
+/// Time kept back from the Lambda deadline to build and send the response once the search stops.
+pub(crate) const DEADLINE_SAFETY_MARGIN: Duration = Duration::from_millis(500);
 
-    let option_best_move = tokio::task::spawn_blocking(move || {
+/// `deadline` is when the Lambda invocation will be killed, if known.
+/// The search is stopped early enough to still respond, returning the best move found so far.
+pub async fn best_move(input: BestMoveInput, deadline: Option<SystemTime>) -> Result<BestMoveResponse, ResponseError> {
+    let position = Position::from_variant_fen(input.variant, &input.fen)?;
+
+    let multipv = input.multipv.unwrap_or(1);
+    if !(1..=MAX_MULTIPV).contains(&multipv) {
+        return Err(ResponseError::invalid_field("multipv", format!("multipv must be between 1 and {}.", MAX_MULTIPV)))
+    }
+
+    if input.nodes == Some(0) {
+        return Err(ResponseError::invalid_field("nodes", "nodes must be greater than 0."))
+    }
+
+    let skill = match (input.skill_level, input.elo) {
+        (Some(_), Some(_)) => {
+            return Err(ResponseError::new(ErrorCode::InvalidRequest, "Only one of skill_level and elo can be given.")
+                .with_details(serde_json::json!({ "fields": ["skill_level", "elo"] })))
+        }
+        (Some(level), None) if level > MAX_SKILL_LEVEL => {
+            return Err(ResponseError::invalid_field("skill_level", format!("skill_level must be between 0 and {}.", MAX_SKILL_LEVEL)))
+        }
+        (Some(level), None) => Skill::new(level),
+        (None, Some(elo)) => Skill::from_elo(elo),
+        (None, None) => Skill::new(MAX_SKILL_LEVEL),
+    };
+
+    let mut engine = Engine::new(position.clone())
+        .with_multipv(multipv)
+        .with_skill(skill);
+
+    if let Some(threads) = engine_threads() {
+        engine = engine.with_threads(threads);
+    }
+
+    if let Some(nodes) = input.nodes {
+        engine = engine.with_node_limit(nodes);
+    }
+
+    if let Some(seed) = input.seed {
+        engine = engine.with_seed(seed);
+    }
+
+    // With a clock the time manager decides how deep to search.
+    if let Some(clock) = input.clock {
+        let (time, increment) = match position.turn() {
+            Colour::White => (clock.white_time, clock.white_increment),
+            Colour::Black => (clock.black_time, clock.black_increment),
+        };
+
+        let time_control = TimeControl {
+            time: Duration::from_millis(time),
+            increment: Duration::from_millis(increment.unwrap_or(0)),
+            moves_to_go: clock.moves_to_go,
+        };
+
+        engine = engine
+            .with_depth(MAX_SEARCH_DEPTH)
+            .with_time_manager(TimeManager::for_clock(&time_control));
+    }
+
+    let stop = Arc::new(AtomicBool::new(false));
+    engine = engine.with_stop(stop.clone());
+
+    let search = tokio::task::spawn_blocking(move || {
         engine.best_move()
-    }).await.unwrap();
+    });
+
+    let option_best_move = search_until_deadline(search, &stop, deadline).await?;
 
     match option_best_move {
-        None => Err(ResponseError { error: String::from("No Legal Moves.")}),
+        None => Err(ResponseError::new(ErrorCode::NoLegalMoves, "The game is over, there are no legal moves.")),
 
         Some(best_move) => { 
-            let mut new_board = best_move.resulting_board;
-            let resulting_legal_moves = get_resulting_game_states(&mut new_board);
+            let candidate_moves = best_move.lines.iter()
+                .map(|line| {
+                    let san_pv = position.san_line(&line.pv);
+
+                    CandidateMove {
+                        uci_move: line.pv[0].uci(),
+                        san_move: san_pv[0].clone(),
+                        score: line.score,
+                        depth: line.depth,
+                        pv: line.pv.iter().map(|mv| mv.uci()).collect(),
+                        san_pv,
+                    }
+                })
+                .collect();
+
+            let san_move = position.san(best_move.chosen_move);
+            let mut new_position = best_move.resulting_position;
+            let resulting_legal_moves = get_resulting_game_states(&mut new_position)?;
 
             Ok(BestMoveResponse {
-                game_over: game_over(&new_board),
-                uci_move: best_move.uci_move,
-                san_move: best_move.san_move,
-                resulting_fen: new_board.fen(),
+                game_over: new_position.game_over(),
+                uci_move: best_move.chosen_move.uci(),
+                san_move,
+                resulting_fen: new_position.fen(),
                 resulting_legal_moves,
+                candidate_moves,
+                seed: best_move.seed,
+                truncated: best_move.truncated,
             })
         }
     }
+}
+
+/// Waits for a search started with `stop` as its stop flag. If the search is still running
+/// `DEADLINE_SAFETY_MARGIN` before `deadline`, it is stopped and returns the best move found so far.
+pub(crate) async fn search_until_deadline<T>(mut search: JoinHandle<T>, stop: &AtomicBool, deadline: Option<SystemTime>) -> Result<T, ResponseError> {
+    match deadline {
+        Some(deadline) => {
+            let search_time = deadline.duration_since(SystemTime::now())
+                .unwrap_or_default()
+                .saturating_sub(DEADLINE_SAFETY_MARGIN);
+
+            tokio::select! {
+                result = &mut search => result,
+                _ = tokio::time::sleep(search_time) => {
+                    stop.store(true, Ordering::Relaxed);
+                    search.await
+                }
+            }
+        }
+        None => search.await,
+    }.map_err(|e| ResponseError::internal(format!("The search failed: {}", e)))
+}
+
+/// Number of search threads set through the `ENGINE_THREADS` environment variable.
+/// When unset, the engine uses every core available to the Lambda.
+pub(crate) fn engine_threads() -> Option<usize> {
+    std::env::var("ENGINE_THREADS").ok()?.parse().ok()
 }
\ No newline at end of file
@@ -0,0 +1,181 @@
+use std::{sync::{atomic::AtomicBool, Arc}, time::SystemTime};
+use crate::{
+    bot::{
+        engine::Engine,
+        heuristics::{king_safety, piece_square_value},
+        transposition::{TranspositionTable, DEFAULT_TRANSPOSITION_TABLE_MB},
+    },
+    handlers::best_move::{engine_threads, search_until_deadline},
+    position::{Move, Position},
+    tactics::{hanging_pieces, hanging_value, static_exchange, with_side_to_move},
+    types::{ErrorCode, HintInput, HintReason, HintReasonKind, HintResponse, ResponseError, Score},
+};
+
+/// Plies searched for a mate threat, enough to find a mate in 2.
+const MATE_THREAT_DEPTH: u8 = 3;
+
+/// Keeps the mate threat search cheap next to the main one.
+const MATE_THREAT_NODES: u64 = 20_000;
+
+/// What the searches of a single hint share.
+struct SearchResources {
+    transposition_table: Arc<TranspositionTable>,
+    /// Set when the request is about to run out of time.
+    stop: Arc<AtomicBool>,
+}
+
+/// Finds the best move, then explains it from the search and the evaluation's terms.
+/// Like `best_move`, the search is stopped in time to respond before `deadline`.
+pub async fn hint(input: HintInput, deadline: Option<SystemTime>) -> Result<HintResponse, ResponseError> {
+    let position = Position::from_variant_fen(input.variant, &input.fen)?;
+
+    if input.nodes == Some(0) {
+        return Err(ResponseError::invalid_field("nodes", "nodes must be greater than 0."))
+    }
+
+    // The mate threat search reuses the table and stops with the main search.
+    let search = SearchResources {
+        transposition_table: Arc::new(TranspositionTable::new(DEFAULT_TRANSPOSITION_TABLE_MB)),
+        stop: Arc::new(AtomicBool::new(false)),
+    };
+
+    let mut engine = Engine::new(position.clone())
+        .with_transposition_table(search.transposition_table.clone())
+        .with_stop(search.stop.clone());
+
+    if let Some(threads) = engine_threads() {
+        engine = engine.with_threads(threads);
+    }
+
+    if let Some(nodes) = input.nodes {
+        engine = engine.with_node_limit(nodes);
+    }
+
+    let option_best_move = search_until_deadline(tokio::task::spawn_blocking(move || engine.best_move()), &search.stop, deadline).await?;
+
+    let best_move = option_best_move
+        .ok_or_else(|| ResponseError::new(ErrorCode::NoLegalMoves, "The game is over, there are no legal moves."))?;
+    let line = best_move.lines.first()
+        .ok_or_else(|| ResponseError::internal(format!("The search of {} found no lines", position.fen())))?;
+
+    let san_pv = position.san_line(&line.pv);
+    let reasons = reasons(&position, line.pv[0], line.score, &search, deadline).await?
+        .into_iter()
+        .map(|kind| HintReason { text: text(&kind), kind })
+        .collect();
+
+    Ok(HintResponse {
+        uci_move: line.pv[0].uci(),
+        san_move: san_pv[0].clone(),
+        score: line.score,
+        pv: line.pv.iter().map(|mv| mv.uci()).collect(),
+        san_pv,
+        reasons,
+    })
+}
+
+/// Why `mv` is good, most important first. `score` is what the search found for it.
+async fn reasons(
+    position: &Position,
+    mv: Move,
+    score: Score,
+    search: &SearchResources,
+    deadline: Option<SystemTime>,
+) -> Result<Vec<HintReasonKind>, ResponseError> {
+    let mover = position.turn();
+    let mut after = position.clone();
+    after.play(mv);
+
+    let piece = position.moved_piece(mv)
+        .ok_or_else(|| ResponseError::internal(format!("There is no piece to move for {} in {}", mv, position.fen())))?;
+
+    let mut reasons = Vec::new();
+    let mating = after.is_checkmate() || matches!(score, Score::Mate(moves) if moves > 0);
+
+    match score {
+        _ if after.is_checkmate() => reasons.push(HintReasonKind::Checkmate),
+        Score::Mate(moves) if moves > 0 => reasons.push(HintReasonKind::ForcesMate { moves }),
+        _ => {}
+    }
+
+    if position.is_capture(mv) && !mv.is_en_passant() {
+        let see = static_exchange(position, mv.from(), mv.to());
+        if see > 0 {
+            reasons.push(HintReasonKind::WinsMaterial { see });
+        }
+    }
+
+    if let Some(promotion) = mv.promotion() {
+        reasons.push(HintReasonKind::Promotes { piece: promotion });
+    }
+
+    if !mating && !after.in_check() && let Some(moves) = mate_threat(&after, search, deadline).await? {
+        reasons.push(HintReasonKind::ThreatensMate { moves });
+    }
+
+    for (square, _) in hanging_pieces(position, mover) {
+        let Some((_, hanging_piece)) = position.piece_at(square) else { continue };
+
+        if square == mv.from() && !mv.is_castle() {
+            if hanging_value(&after, mv.to()).is_none() {
+                reasons.push(HintReasonKind::SavesPiece { piece, from: square.to_string(), to: mv.to().to_string() });
+            }
+        } else if after.piece_at(square) == position.piece_at(square) && hanging_value(&after, square).is_none() {
+            reasons.push(HintReasonKind::DefendsPiece { piece: hanging_piece, square: square.to_string() });
+        }
+    }
+
+    if after.in_check() && !after.is_checkmate() {
+        reasons.push(HintReasonKind::GivesCheck);
+    }
+
+    let (king_safety_before, king_safety_after) = (king_safety(position, mover), king_safety(&after, mover));
+    if king_safety_after > king_safety_before {
+        reasons.push(HintReasonKind::ImprovesKingSafety { before: king_safety_before, after: king_safety_after });
+    }
+
+    // Only worth saying when nothing more concrete explains the move.
+    if reasons.is_empty() && !mv.is_castle() && mv.promotion().is_none() {
+        let gain = piece_square_value(&after, mv.to()) - piece_square_value(position, mv.from());
+        if gain > 0 {
+            reasons.push(HintReasonKind::ImprovesPiece { piece, gain });
+        }
+    }
+
+    Ok(reasons)
+}
+
+/// The mate the side that just moved would have if it could move again, such as after the other
+/// side passes, found with a short search. Like the main search, it is stopped before `deadline`.
+async fn mate_threat(after: &Position, search: &SearchResources, deadline: Option<SystemTime>) -> Result<Option<i32>, ResponseError> {
+    let Some(passed) = with_side_to_move(after, !after.turn()) else { return Ok(None) };
+    let mut engine = Engine::new(passed)
+        .with_depth(MATE_THREAT_DEPTH)
+        .with_node_limit(MATE_THREAT_NODES)
+        .with_transposition_table(search.transposition_table.clone())
+        .with_stop(search.stop.clone());
+
+    let option_best_move = search_until_deadline(tokio::task::spawn_blocking(move || engine.best_move()), &search.stop, deadline).await?;
+
+    Ok(option_best_move.and_then(|best_move| match best_move.lines.first()?.score {
+        Score::Mate(moves) if moves > 0 => Some(moves),
+        _ => None,
+    }))
+}
+
+fn text(kind: &HintReasonKind) -> String {
+    let name = |piece| format!("{:?}", piece).to_lowercase();
+
+    match kind {
+        HintReasonKind::Checkmate => String::from("delivers checkmate"),
+        HintReasonKind::ForcesMate { moves } => format!("forces mate in {}", moves),
+        HintReasonKind::WinsMaterial { see } => format!("wins material (SEE +{})", see),
+        HintReasonKind::Promotes { piece } => format!("promotes to a {}", name(piece)),
+        HintReasonKind::ThreatensMate { moves } => format!("threatens mate in {}", moves),
+        HintReasonKind::SavesPiece { piece, from, to } => format!("moves the hanging {} on {} to safety on {}", name(piece), from, to),
+        HintReasonKind::DefendsPiece { piece, square } => format!("defends hanging {} on {}", name(piece), square),
+        HintReasonKind::GivesCheck => String::from("gives check"),
+        HintReasonKind::ImprovesKingSafety { .. } => String::from("improves king safety"),
+        HintReasonKind::ImprovesPiece { piece, gain } => format!("puts the {} on a better square (+{})", name(piece), gain),
+    }
+}
\ No newline at end of file
@@ -1,18 +1,16 @@
-use pleco::Board;
-use crate::{types::{LegalMoves, ResponseError}, utils::{game_over, get_resulting_game_states}};
+use crate::{position::Position, types::{LegalMoves, ResponseError, Variant}, utils::get_resulting_game_states};
 
-fn generate_legal_moves(mut board: Board) -> LegalMoves {
-    let legal_moves= get_resulting_game_states(&mut board);
+fn generate_legal_moves(mut position: Position) -> Result<LegalMoves, ResponseError> {
+    let legal_moves= get_resulting_game_states(&mut position)?;
 
-    LegalMoves { 
-        game_over: game_over(&board),
+    Ok(LegalMoves { 
+        game_over: position.game_over(),
         legal_moves
-    }
+    })
 }
 
-pub async fn legal_moves(fen: String) -> Result<LegalMoves, ResponseError> {
-    let board = Board::from_fen(&fen)
-        .map_err(|e| ResponseError { error: format!("{:?}", e)})?;
+pub async fn legal_moves(fen: String, variant: Variant) -> Result<LegalMoves, ResponseError> {
+    let position = Position::from_variant_fen(variant, &fen)?;
 
-    Ok(generate_legal_moves(board))
+    generate_legal_moves(position)
 }
\ No newline at end of file
@@ -1,4 +1,11 @@
+pub mod apply_move;
 pub mod best_move;
 pub mod health_check;
+pub mod hint;
 pub mod legal_moves;
+pub mod perft;
+pub mod pgn_export;
+pub mod pgn_parse;
+pub mod review;
+pub mod threats;
 pub mod validate_fen;
\ No newline at end of file
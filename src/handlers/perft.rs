@@ -0,0 +1,98 @@
+use std::{sync::{atomic::{AtomicBool, Ordering}, Arc}, thread, time::SystemTime};
+use crate::{
+    bot::engine::default_threads,
+    handlers::best_move::{engine_threads, search_until_deadline},
+    position::{Move, Position},
+    types::{ErrorCode, PerftInput, PerftMove, PerftResponse, ResponseError},
+};
+
+/// Deeper counts take hours. Even depth 6 of a busy position outlasts a Lambda, and is stopped at the deadline.
+const MAX_PERFT_DEPTH: u8 = 6;
+
+/// Subtrees this shallow are left to the chess library, which counts them in a few milliseconds at most.
+const UNINTERRUPTED_DEPTH: u16 = 3;
+
+/// Counts the positions `depth` plies from the FEN, in total and after each legal move.
+/// A count still running shortly before `deadline` is abandoned with a `TIMEOUT` error.
+pub async fn perft(input: PerftInput, deadline: Option<SystemTime>) -> Result<PerftResponse, ResponseError> {
+    let position = Position::from_variant_fen(input.variant, &input.fen)?;
+
+    if !(1..=MAX_PERFT_DEPTH).contains(&input.depth) {
+        return Err(ResponseError::invalid_field("depth", format!("depth must be between 1 and {}.", MAX_PERFT_DEPTH)))
+    }
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let divided_position = position.clone();
+    let divide_stop = stop.clone();
+    let counting = tokio::task::spawn_blocking(move || divide(&divided_position, input.depth as u16, &divide_stop));
+
+    let counts = search_until_deadline(counting, &stop, deadline).await??
+        .ok_or_else(|| ResponseError::new(ErrorCode::Timeout, format!("The count to depth {} did not finish in time, try a lower depth.", input.depth)))?;
+
+    let mut divide: Vec<PerftMove> = counts.into_iter()
+        .map(|(mv, nodes)| PerftMove { uci_move: mv.uci(), san_move: position.san(mv), nodes })
+        .collect();
+    divide.sort_by(|a, b| a.uci_move.cmp(&b.uci_move));
+
+    Ok(PerftResponse {
+        nodes: divide.iter().map(|perft_move| perft_move.nodes).sum(),
+        divide,
+    })
+}
+
+/// The leaf nodes after each legal move, with the moves shared out between threads.
+/// `None` if `stop` was set before the count finished.
+fn divide(position: &Position, depth: u16, stop: &AtomicBool) -> Result<Option<Vec<(Move, u64)>>, ResponseError> {
+    let moves = position.legal_moves();
+    let threads = engine_threads().unwrap_or_else(default_threads).max(1);
+    let moves_per_thread = moves.len().div_ceil(threads).max(1);
+
+    thread::scope(|scope| {
+        let handles: Vec<_> = moves.chunks(moves_per_thread)
+            .map(|chunk| {
+                let mut position = position.clone();
+                scope.spawn(move || {
+                    chunk.iter()
+                        .map(|&mv| {
+                            position.play(mv);
+                            let nodes = count(&mut position, depth - 1, stop);
+                            position.undo();
+                            nodes.map(|nodes| (mv, nodes))
+                        })
+                        .collect::<Option<Vec<_>>>()
+                })
+            })
+            .collect();
+
+        let mut counts = Vec::new();
+        for handle in handles {
+            match handle.join().map_err(|_| ResponseError::internal(format!("A perft thread of {} panicked", position.fen())))? {
+                Some(chunk_counts) => counts.extend(chunk_counts),
+                None => return Ok(None),
+            }
+        }
+
+        Ok(Some(counts))
+    })
+}
+
+/// Counts like `Position::perft`, checking `stop` before each subtree handed to the chess library.
+fn count(position: &mut Position, depth: u16, stop: &AtomicBool) -> Option<u64> {
+    if stop.load(Ordering::Relaxed) {
+        return None
+    }
+
+    if depth <= UNINTERRUPTED_DEPTH {
+        return Some(position.perft(depth))
+    }
+
+    let mut nodes = 0;
+    for mv in position.legal_moves() {
+        position.play(mv);
+        let subtree_nodes = count(position, depth - 1, stop);
+        position.undo();
+        nodes += subtree_nodes?;
+    }
+
+    Some(nodes)
+}
\ No newline at end of file
@@ -0,0 +1,5 @@
+use crate::{pgn::export_pgn, types::{PgnExportInput, PgnExportResponse, ResponseError}};
+
+pub async fn pgn_export(input: PgnExportInput) -> Result<PgnExportResponse, ResponseError> {
+    Ok(PgnExportResponse { pgn: export_pgn(input)? })
+}
\ No newline at end of file
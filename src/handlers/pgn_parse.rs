@@ -0,0 +1,5 @@
+use crate::{pgn::parse_pgn, types::{PgnParseResponse, ResponseError}};
+
+pub async fn pgn_parse(pgn: String) -> Result<PgnParseResponse, ResponseError> {
+    Ok(PgnParseResponse { games: parse_pgn(&pgn)? })
+}
\ No newline at end of file
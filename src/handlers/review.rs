@@ -0,0 +1,228 @@
+use std::{sync::Arc, time::{Duration, Instant, SystemTime}};
+use crate::{
+    bot::{engine::{Engine, MAX_SEARCH_DEPTH}, time_manager::{TimeManager, MOVE_OVERHEAD}, transposition::{TranspositionTable, DEFAULT_TRANSPOSITION_TABLE_MB}},
+    handlers::best_move::{engine_threads, DEADLINE_SAFETY_MARGIN},
+    pgn::parse_pgn,
+    position::{Move, Position, START_FEN},
+    types::{Colour, ErrorCode, MoveClassification, ResponseError, ReviewInput, ReviewResponse, ReviewSummary, ReviewedMove, Score, Variant},
+};
+
+/// Time spent on a review when the request does not say.
+const DEFAULT_TIME_BUDGET: Duration = Duration::from_secs(10);
+
+/// The most time a single request can ask for.
+const MAX_TIME_BUDGET: Duration = Duration::from_secs(600);
+
+/// Evaluations are capped at this many centipawns either way before comparing moves, with mates
+/// counted as the cap, so a won position that stays won is not punished for winning less.
+const CENTIPAWN_CAP: i32 = 1000;
+
+/// The least centipawn loss for each classification worse than `Good`.
+const INACCURACY_THRESHOLD: u32 = 50;
+const MISTAKE_THRESHOLD: u32 = 100;
+const BLUNDER_THRESHOLD: u32 = 300;
+
+/// Searches every position of the game, then compares each played move with the engine's best move.
+///
+/// The time budget is shared between the positions, and time a search leaves unused goes to the
+/// positions after it. `deadline` is when the Lambda invocation will be killed, if known, and the
+/// budget is cut to end before it.
+pub async fn review(input: ReviewInput, deadline: Option<SystemTime>) -> Result<ReviewResponse, ResponseError> {
+    let (position, moves) = game(input.pgn, input.fen, input.moves, input.variant)?;
+
+    let mut budget = match input.time_budget_ms {
+        Some(0) => return Err(ResponseError::invalid_field("time_budget_ms", "time_budget_ms must be greater than 0.")),
+        Some(time_budget_ms) => Duration::from_millis(time_budget_ms).min(MAX_TIME_BUDGET),
+        None => DEFAULT_TIME_BUDGET,
+    };
+
+    if let Some(deadline) = deadline {
+        let remaining = deadline.duration_since(SystemTime::now())
+            .unwrap_or_default()
+            .saturating_sub(DEADLINE_SAFETY_MARGIN);
+        budget = budget.min(remaining);
+    }
+
+    let mut positions = vec![position];
+    let mut played_moves = Vec::new();
+
+    for (index, notation) in moves.iter().enumerate() {
+        let mut position = positions[index].clone();
+        let mv = position.parse_move(notation).map_err(|e| match e.code {
+            ErrorCode::IllegalMove => e.with_details(serde_json::json!({ "move": notation, "move_index": index })),
+            _ => e,
+        })?;
+
+        position.play(mv);
+        played_moves.push(mv);
+        positions.push(position);
+    }
+
+    let searched_positions = positions.clone();
+    let searched_moves = played_moves.clone();
+    let evaluations = tokio::task::spawn_blocking(move || evaluate_moves(&searched_positions, &searched_moves, budget))
+        .await
+        .map_err(|e| ResponseError::internal(format!("The review failed: {}", e)))?;
+
+    let mut reviewed_moves = Vec::new();
+
+    for (index, (mv, evaluation)) in played_moves.iter().zip(evaluations).enumerate() {
+        let position = &positions[index];
+        let fen = position.fen();
+
+        let (best_move, best_eval, eval) = evaluation
+            .ok_or_else(|| ResponseError::internal(format!("There is no evaluation of {} in {}", mv, fen)))?;
+
+        let centipawn_loss = (centipawns(best_eval) - centipawns(eval)).max(0) as u32;
+        let classification = match centipawn_loss {
+            _ if *mv == best_move => MoveClassification::Best,
+            loss if loss >= BLUNDER_THRESHOLD => MoveClassification::Blunder,
+            loss if loss >= MISTAKE_THRESHOLD => MoveClassification::Mistake,
+            loss if loss >= INACCURACY_THRESHOLD => MoveClassification::Inaccuracy,
+            0 => MoveClassification::Best,
+            _ => MoveClassification::Good,
+        };
+
+        reviewed_moves.push(ReviewedMove {
+            ply: index + 1,
+            move_number: position.move_number(),
+            colour: position.turn(),
+            uci_move: mv.uci(),
+            san_move: position.san(*mv),
+            eval,
+            best_uci_move: best_move.uci(),
+            best_san_move: position.san(best_move),
+            best_eval,
+            centipawn_loss,
+            classification,
+        });
+    }
+
+    Ok(ReviewResponse {
+        starting_fen: positions[0].fen(),
+        white: summary(&reviewed_moves, Colour::White),
+        black: summary(&reviewed_moves, Colour::Black),
+        moves: reviewed_moves,
+    })
+}
+
+/// The position and moves to review, from either a PGN or a FEN and move list.
+fn game(pgn: Option<String>, fen: Option<String>, moves: Option<Vec<String>>, variant: Variant) -> Result<(Position, Vec<String>), ResponseError> {
+    match (pgn, fen, moves) {
+        (Some(pgn), None, None) => {
+            let mut games = parse_pgn(&pgn)?;
+            if games.len() > 1 {
+                return Err(ResponseError::invalid_field("pgn", "Only one game can be reviewed at a time."))
+            }
+
+            let game = games.remove(0);
+            let moves = game.moves.into_iter().map(|ply| ply.uci_move).collect();
+
+            Ok((Position::from_variant_fen(game.variant, &game.starting_fen)?, moves))
+        }
+        (None, fen, Some(moves)) => Ok((Position::from_variant_fen(variant, fen.as_deref().unwrap_or(START_FEN))?, moves)),
+        (None, _, None) => Err(ResponseError::invalid_field("moves", "Either pgn or moves must be given.")),
+        (Some(_), _, _) => Err(ResponseError::new(ErrorCode::InvalidRequest, "pgn cannot be combined with fen and moves.")
+            .with_details(serde_json::json!({ "fields": ["pgn", "fen", "moves"] }))),
+    }
+}
+
+/// Searches the position before each played move, returning the best move, its score, and the score
+/// of the played move. The played move is searched on its own from the same position rather than
+/// scored from the position after it, so both scores come from the same side's evaluation.
+fn evaluate_moves(positions: &[Position], played_moves: &[Move], budget: Duration) -> Vec<Option<(Move, Score, Score)>> {
+    let start = Instant::now();
+    let transposition_table = Arc::new(TranspositionTable::new(DEFAULT_TRANSPOSITION_TABLE_MB));
+    // At most two searches per move, the time of any not needed goes to the ones after.
+    let mut searches_left = 2 * played_moves.len() as u32;
+    let mut evaluations = Vec::new();
+
+    for (position, played_move) in positions.iter().zip(played_moves) {
+        let move_time = budget.saturating_sub(start.elapsed()) / searches_left.max(1);
+        let best = search(position, None, move_time, &transposition_table);
+        searches_left -= 1;
+
+        let evaluation = match best {
+            Some((best_move, best_eval)) if best_move == *played_move => {
+                searches_left -= 1;
+                Some((best_move, best_eval, best_eval))
+            }
+            Some((best_move, best_eval)) => {
+                let move_time = budget.saturating_sub(start.elapsed()) / searches_left.max(1);
+                let played = search(position, Some(vec![*played_move]), move_time, &transposition_table);
+                searches_left -= 1;
+
+                played.map(|(_, eval)| (best_move, best_eval, eval))
+            }
+            None => None,
+        };
+
+        evaluations.push(evaluation);
+    }
+
+    evaluations
+}
+
+/// The first move of the best line and its score, searching only `root_moves` if given.
+fn search(position: &Position, root_moves: Option<Vec<Move>>, move_time: Duration, transposition_table: &Arc<TranspositionTable>) -> Option<(Move, Score)> {
+    // `TimeManager::fixed` keeps back time for the network, which only matters once per request.
+    let mut engine = Engine::new(position.clone())
+        .with_depth(MAX_SEARCH_DEPTH)
+        .with_time_manager(TimeManager::fixed(move_time + MOVE_OVERHEAD))
+        .with_transposition_table(transposition_table.clone());
+
+    if let Some(threads) = engine_threads() {
+        engine = engine.with_threads(threads);
+    }
+
+    if let Some(root_moves) = root_moves {
+        engine = engine.with_root_moves(root_moves);
+    }
+
+    let best_move = engine.best_move()?;
+    best_move.lines.first().map(|line| (line.pv[0], line.score))
+}
+
+fn centipawns(score: Score) -> i32 {
+    match score {
+        Score::Centipawns(centipawns) => centipawns.clamp(-CENTIPAWN_CAP, CENTIPAWN_CAP),
+        Score::Mate(moves) if moves > 0 => CENTIPAWN_CAP,
+        Score::Mate(_) => -CENTIPAWN_CAP,
+    }
+}
+
+/// The chance of winning, from 0 to 100, of the side with this many centipawns.
+/// The curve is fitted to real games, see https://lichess.org/page/accuracy.
+fn win_percent(centipawns: i32) -> f64 {
+    50.0 + 50.0 * (2.0 / (1.0 + (-0.00368208 * centipawns as f64).exp()) - 1.0)
+}
+
+/// How close a move was to the best move, from 0 to 100, by how much it lowered the chance of winning.
+fn move_accuracy(reviewed_move: &ReviewedMove) -> f64 {
+    let lost = win_percent(centipawns(reviewed_move.best_eval)) - win_percent(centipawns(reviewed_move.eval));
+
+    (103.1668 * (-0.04354 * lost.max(0.0)).exp() - 3.1669).clamp(0.0, 100.0)
+}
+
+fn summary(reviewed_moves: &[ReviewedMove], colour: Colour) -> ReviewSummary {
+    let moves: Vec<&ReviewedMove> = reviewed_moves.iter().filter(|reviewed_move| reviewed_move.colour == colour).collect();
+    let count = |classification| moves.iter().filter(|reviewed_move| reviewed_move.classification == classification).count();
+
+    let (accuracy, average_centipawn_loss) = if moves.is_empty() {
+        (None, None)
+    } else {
+        let accuracy = moves.iter().map(|reviewed_move| move_accuracy(reviewed_move)).sum::<f64>() / moves.len() as f64;
+        let centipawn_loss = moves.iter().map(|reviewed_move| reviewed_move.centipawn_loss).sum::<u32>() / moves.len() as u32;
+
+        (Some((accuracy * 10.0).round() / 10.0), Some(centipawn_loss))
+    };
+
+    ReviewSummary {
+        moves: moves.len(),
+        accuracy,
+        average_centipawn_loss,
+        inaccuracies: count(MoveClassification::Inaccuracy),
+        mistakes: count(MoveClassification::Mistake),
+        blunders: count(MoveClassification::Blunder),
+    }
+}
\ No newline at end of file
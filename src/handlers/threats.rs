@@ -0,0 +1,85 @@
+use crate::{
+    position::{Position, Square},
+    tactics::{discovered_attacks, forks, hanging_pieces, lower_valued_attackers, pins, with_side_to_move},
+    types::{AttackedPiece, Colour, DiscoveredAttack, Fork, HangingPiece, PinnedPiece, PlacedPiece, ResponseError, SideThreats, ThreatsResponse, Variant},
+};
+
+pub async fn threats(fen: String, variant: Variant) -> Result<ThreatsResponse, ResponseError> {
+    let position = Position::from_variant_fen(variant, &fen)?;
+
+    Ok(ThreatsResponse {
+        side_to_move: side_threats(&position, position.turn())?,
+        opponent: side_threats(&position, !position.turn())?,
+    })
+}
+
+/// The threats against the pieces of `side`, and the tactics it has. The opponent's tactics are
+/// found as if the side to move had passed, so there are none while the side to move is in check.
+fn side_threats(position: &Position, side: Colour) -> Result<SideThreats, ResponseError> {
+    let mut hanging = Vec::new();
+    for (square, value) in hanging_pieces(position, side) {
+        let PlacedPiece { piece, square } = placed_piece(position, square)?;
+        hanging.push(HangingPiece { piece, square, value });
+    }
+
+    let mut attacked_by_lower = Vec::new();
+    for square in position.occupied_by(side) {
+        let attackers = lower_valued_attackers(position, square);
+        if attackers.is_empty() {
+            continue;
+        }
+
+        let PlacedPiece { piece, square } = placed_piece(position, square)?;
+        let attackers = attackers.into_iter().map(|attacker| placed_piece(position, attacker)).collect::<Result<_, _>>()?;
+        attacked_by_lower.push(AttackedPiece { piece, square, attackers });
+    }
+
+    let mut pinned = Vec::new();
+    for (square, pinner) in pins(position, side) {
+        let PlacedPiece { piece, square } = placed_piece(position, square)?;
+        pinned.push(PinnedPiece { piece, square, pinned_by: placed_piece(position, pinner)? });
+    }
+
+    let mut discovered = Vec::new();
+    let mut forking_moves = Vec::new();
+
+    if let Some(position) = with_side_to_move(position, side) {
+        for (blocker, attacker, target) in discovered_attacks(&position) {
+            let PlacedPiece { piece, square } = placed_piece(&position, blocker)?;
+            discovered.push(DiscoveredAttack {
+                piece,
+                square,
+                attacker: placed_piece(&position, attacker)?,
+                target: placed_piece(&position, target)?,
+            });
+        }
+
+        for (mv, targets) in forks(&position) {
+            let mut after = position.clone();
+            after.play(mv);
+
+            forking_moves.push(Fork {
+                uci_move: mv.uci(),
+                san_move: position.san(mv),
+                piece: placed_piece(&after, mv.to())?.piece,
+                targets: targets.into_iter().map(|target| placed_piece(&after, target)).collect::<Result<_, _>>()?,
+            });
+        }
+    }
+
+    Ok(SideThreats {
+        colour: side,
+        hanging,
+        attacked_by_lower,
+        pinned,
+        discovered_attacks: discovered,
+        forks: forking_moves,
+    })
+}
+
+fn placed_piece(position: &Position, square: Square) -> Result<PlacedPiece, ResponseError> {
+    let (_, piece) = position.piece_at(square)
+        .ok_or_else(|| ResponseError::internal(format!("There is no piece on {} in {}", square, position.fen())))?;
+
+    Ok(PlacedPiece { piece, square: square.to_string() })
+}
\ No newline at end of file
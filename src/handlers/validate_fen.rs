@@ -1,13 +1,8 @@
-use pleco::Board;
+use crate::{position::Position, types::{ResponseError, ValidateFenResponse, Variant}};
 
-use crate::types::{ResponseError, ValidateFenResponse};
-
-pub async fn validate_fen(fen: String) -> Result<ValidateFenResponse, ResponseError> {
-    match Board::from_fen(&fen) {
+pub async fn validate_fen(fen: String, variant: Variant) -> Result<ValidateFenResponse, ResponseError> {
+    match Position::from_variant_fen(variant, &fen) {
         Ok(_) => Ok(ValidateFenResponse {valid: true, error: None}),
-        Err(e) => {
-            let error = format!("{:?}",e);
-            Ok(ValidateFenResponse {valid: false, error: Some(error)})
-        }
+        Err(e) => Ok(ValidateFenResponse {valid: false, error: Some(e)}),
     }
 }
\ No newline at end of file
@@ -0,0 +1,9 @@
+pub mod handlers;
+pub mod pgn;
+pub mod position;
+pub mod bot;
+pub mod cors;
+pub mod router;
+pub mod tactics;
+pub mod utils;
+pub mod types;
\ No newline at end of file
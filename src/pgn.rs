@@ -0,0 +1,564 @@
+use std::{iter::Peekable, mem, vec::IntoIter};
+use serde::Serialize;
+
+use crate::{
+    position::{is_san, Position, START_FEN},
+    types::{Colour, ErrorCode, GameOver, PgnExportInput, PgnGame, PgnMoveAnnotation, PgnPly, PgnTag, ResponseError, Score, Variant},
+    utils::resulting_game_state,
+};
+
+/// Movetext lines are wrapped to fit in this many characters, as the PGN export format asks.
+const MAX_LINE_LENGTH: usize = 79;
+
+/// How deep variations may be nested. Each level is parsed by a nested call, so without a limit
+/// a long run of opening parentheses would overflow the stack. Each level also nests the response
+/// JSON three deeper, and this keeps it within the 128 levels serde_json and others will read.
+const MAX_VARIATION_DEPTH: usize = 32;
+
+/// The Seven Tag Roster, which every exported game starts with, and the value of each when unknown.
+/// `Result` is filled in from the moves.
+const SEVEN_TAG_ROSTER: [(&str, &str); 7] = [
+    ("Event", "?"),
+    ("Site", "?"),
+    ("Date", "????.??.??"),
+    ("Round", "?"),
+    ("White", "?"),
+    ("Black", "?"),
+    ("Result", "*"),
+];
+
+/// Where in the PGN text something was found. Lines and columns start at 1, columns count characters.
+#[derive(Serialize, Debug, Clone, Copy)]
+pub struct PgnLocation {
+    pub line: usize,
+    pub column: usize,
+    /// Byte offset into the PGN text.
+    pub offset: usize,
+}
+
+#[derive(Debug)]
+enum TokenKind {
+    Tag { name: String, value: String },
+    Comment(String),
+    Nag(u8),
+    VariationStart,
+    VariationEnd,
+    San(String),
+    Result(String),
+}
+
+#[derive(Debug)]
+struct Token {
+    kind: TokenKind,
+    location: PgnLocation,
+}
+
+/// Parses every game in `pgn`, playing each move on a board so that illegal moves are caught.
+pub fn parse_pgn(pgn: &str) -> Result<Vec<PgnGame>, ResponseError> {
+    let mut tokens = Lexer::new(pgn).tokenize()?.into_iter().peekable();
+    let mut games = Vec::new();
+
+    while tokens.peek().is_some() {
+        games.push(parse_game(&mut tokens, games.len())?);
+    }
+
+    if games.is_empty() {
+        return Err(ResponseError::new(ErrorCode::InvalidPgn, "The PGN does not contain any games."))
+    }
+
+    Ok(games)
+}
+
+fn parse_game(tokens: &mut Peekable<IntoIter<Token>>, game: usize) -> Result<PgnGame, ResponseError> {
+    let mut tags = Vec::new();
+    let mut comments = Vec::new();
+    let mut fen_tag = None;
+    let mut variant = Variant::Standard;
+
+    // Tag pairs, with any comments before the first move.
+    while let Some(token) = tokens.next_if(|token| matches!(token.kind, TokenKind::Tag { .. } | TokenKind::Comment(_))) {
+        match token.kind {
+            TokenKind::Tag { name, value } => {
+                if name == "FEN" {
+                    fen_tag = Some((value.clone(), token.location));
+                }
+                if name == "Variant" && is_chess960(&value) {
+                    variant = Variant::Chess960;
+                }
+                tags.push(PgnTag { name, value });
+            }
+            TokenKind::Comment(comment) => comments.push(comment),
+            _ => {}
+        }
+    }
+
+    let starting_fen = fen_tag.as_ref().map_or(START_FEN, |(fen, _)| fen.as_str());
+    let position = Position::from_variant_fen(variant, starting_fen).map_err(|e| match fen_tag {
+        Some((_, location)) => e.with_details(location_details(Some(game), location, None)),
+        None => e,
+    })?;
+
+    let moves = parse_line(tokens, position.clone(), game, 1, None, 0)?;
+
+    let result = tokens.next_if(|token| matches!(token.kind, TokenKind::Result(_)))
+        .and_then(|token| match token.kind {
+            TokenKind::Result(result) => Some(result),
+            _ => None,
+        });
+
+    Ok(PgnGame {
+        tags,
+        variant,
+        starting_fen: position.fen(),
+        comments,
+        moves,
+        result,
+    })
+}
+
+/// Parses moves from `position` up to the end of the game, or for a variation up to and including
+/// its closing parenthesis. `opening` is where the variation's opening parenthesis is, and `depth`
+/// how many variations it is nested in, counting itself.
+fn parse_line(
+    tokens: &mut Peekable<IntoIter<Token>>,
+    mut position: Position,
+    game: usize,
+    first_ply: usize,
+    opening: Option<PgnLocation>,
+    depth: usize,
+) -> Result<Vec<PgnPly>, ResponseError> {
+    let mut plies: Vec<PgnPly> = Vec::new();
+    let mut previous_position: Option<Position> = None;
+    let mut pending_comments = Vec::new();
+
+    while let Some(token) = tokens.next_if(|token| !ends_line(&token.kind)) {
+        let location = token.location;
+
+        match token.kind {
+            TokenKind::VariationStart => {
+                // A variation is an alternative to the move just played, so it starts from the position before it.
+                let (Some(last_ply), Some(previous_position)) = (plies.last_mut(), &previous_position) else {
+                    return Err(invalid_pgn("Variation before any move.", Some(game), location))
+                };
+
+                if depth == MAX_VARIATION_DEPTH {
+                    let message = format!("Variations are nested more than {} deep.", MAX_VARIATION_DEPTH);
+                    return Err(invalid_pgn(&message, Some(game), location))
+                }
+
+                let variation = parse_line(tokens, previous_position.clone(), game, last_ply.ply, Some(location), depth + 1)?;
+                last_ply.variations.push(variation);
+            }
+            TokenKind::Comment(comment) => match plies.last_mut() {
+                Some(last_ply) => last_ply.comments.push(comment),
+                None => pending_comments.push(comment),
+            },
+            TokenKind::Nag(nag) => match plies.last_mut() {
+                Some(last_ply) => last_ply.nags.push(nag),
+                None => return Err(invalid_pgn("Annotation before any move.", Some(game), location)),
+            },
+            TokenKind::San(san) => {
+                if !is_san(&san) {
+                    return Err(ResponseError::new(ErrorCode::InvalidPgn, format!("{} is not a move in SAN.", san))
+                        .with_details(location_details(Some(game), location, Some(&san))))
+                }
+
+                let mv = position.parse_move(&san).map_err(|e| match e.code {
+                    ErrorCode::IllegalMove => e.with_details(location_details(Some(game), location, Some(&san))),
+                    _ => e,
+                })?;
+                let move_number = position.move_number();
+                let resulting_game_state = resulting_game_state(&mut position, mv)?;
+
+                previous_position = Some(position.clone());
+                position.play(mv);
+
+                plies.push(PgnPly {
+                    ply: first_ply + plies.len(),
+                    move_number,
+                    uci_move: resulting_game_state.uci_move,
+                    san_move: resulting_game_state.san_move,
+                    resulting_fen: resulting_game_state.resulting_fen,
+                    comments: mem::take(&mut pending_comments),
+                    nags: Vec::new(),
+                    variations: Vec::new(),
+                });
+            }
+            TokenKind::Tag { .. } | TokenKind::Result(_) | TokenKind::VariationEnd => {}
+        }
+    }
+
+    let end = tokens.peek().map(|token| (&token.kind, token.location));
+
+    match (end, opening) {
+        (None, None) | (Some((TokenKind::Tag { .. } | TokenKind::Result(_), _)), None) => Ok(plies),
+        (None, Some(opening)) => Err(invalid_pgn("Variation is never closed.", Some(game), opening)),
+        (Some((TokenKind::Tag { .. }, location)), Some(_)) => Err(invalid_pgn("Tag pair inside a variation.", Some(game), location)),
+        (Some((TokenKind::Result(_), location)), Some(_)) => Err(invalid_pgn("Game result inside a variation.", Some(game), location)),
+        (Some((_, location)), None) => Err(invalid_pgn("Closing parenthesis without a variation to close.", Some(game), location)),
+        (Some(_), Some(_)) => {
+            tokens.next();
+            Ok(plies)
+        }
+    }
+}
+
+/// Whether the value of a `Variant` tag names Chess960, which goes by a few names.
+fn is_chess960(variant: &str) -> bool {
+    let name = variant.chars().filter(char::is_ascii_alphanumeric).collect::<String>().to_ascii_lowercase();
+
+    ["chess960", "fischerandom", "fischerrandom", "frc"].contains(&name.as_str())
+}
+
+/// Tokens that end the moves of a game or variation.
+fn ends_line(kind: &TokenKind) -> bool {
+    matches!(kind, TokenKind::Tag { .. } | TokenKind::Result(_) | TokenKind::VariationEnd)
+}
+
+fn invalid_pgn(message: &str, game: Option<usize>, location: PgnLocation) -> ResponseError {
+    ResponseError::new(ErrorCode::InvalidPgn, message).with_details(location_details(game, location, None))
+}
+
+/// `game` is the index of the game in the PGN, when known.
+fn location_details(game: Option<usize>, location: PgnLocation, chess_move: Option<&str>) -> serde_json::Value {
+    let mut details = serde_json::json!({
+        "line": location.line,
+        "column": location.column,
+        "offset": location.offset,
+    });
+
+    if let Some(game) = game {
+        details["game"] = serde_json::Value::from(game);
+    }
+
+    if let Some(chess_move) = chess_move {
+        details["move"] = serde_json::Value::from(chess_move);
+    }
+
+    details
+}
+
+/// Splits PGN text into tokens. Move numbers and the dots after them are dropped, as the moves
+/// are numbered again when they are played.
+struct Lexer<'a> {
+    text: &'a str,
+    offset: usize,
+    line: usize,
+    column: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(text: &'a str) -> Self {
+        Self { text, offset: 0, line: 1, column: 1 }
+    }
+
+    fn tokenize(mut self) -> Result<Vec<Token>, ResponseError> {
+        let mut tokens = Vec::new();
+
+        while let Some(character) = self.peek() {
+            let location = self.location();
+
+            let kind = match character {
+                _ if character.is_whitespace() => {
+                    self.advance();
+                    continue;
+                }
+                // Escape lines are for other programs' private data.
+                '%' if self.column == 1 => {
+                    self.take_while(|character| character != '\n');
+                    continue;
+                }
+                '.' => {
+                    self.advance();
+                    continue;
+                }
+                ';' => {
+                    self.advance();
+                    TokenKind::Comment(self.take_while(|character| character != '\n').trim().to_string())
+                }
+                '{' => {
+                    self.advance();
+                    let comment = self.take_while(|character| character != '}');
+                    if self.advance().is_none() {
+                        return Err(self.error("Comment is never closed.", location))
+                    }
+                    TokenKind::Comment(comment.trim().to_string())
+                }
+                '[' => self.tag(location)?,
+                '(' => {
+                    self.advance();
+                    TokenKind::VariationStart
+                }
+                ')' => {
+                    self.advance();
+                    TokenKind::VariationEnd
+                }
+                '$' => {
+                    self.advance();
+                    let nag = self.take_while(|character| character.is_ascii_digit());
+                    TokenKind::Nag(nag.parse().map_err(|_| self.error("$ must be followed by a number from 0 to 255.", location))?)
+                }
+                '!' | '?' => {
+                    let suffix = self.take_while(|character| character == '!' || character == '?');
+                    let nag = match suffix {
+                        "!" => 1,
+                        "?" => 2,
+                        "!!" => 3,
+                        "??" => 4,
+                        "!?" => 5,
+                        "?!" => 6,
+                        _ => return Err(self.error(&format!("{} is not a move annotation.", suffix), location)),
+                    };
+                    TokenKind::Nag(nag)
+                }
+                '*' => {
+                    self.advance();
+                    TokenKind::Result(String::from("*"))
+                }
+                _ if is_symbol_character(character) => {
+                    let symbol = self.take_while(is_symbol_character);
+
+                    match symbol {
+                        "1-0" | "0-1" | "1/2-1/2" => TokenKind::Result(symbol.to_string()),
+                        _ if symbol.chars().all(|character| character.is_ascii_digit()) => continue,
+                        // Castling is sometimes written with zeros.
+                        _ if symbol.starts_with("0-0") => TokenKind::San(symbol.replace('0', "O")),
+                        _ => TokenKind::San(symbol.to_string()),
+                    }
+                }
+                _ => return Err(self.error(&format!("Unexpected character {:?}.", character), location)),
+            };
+
+            tokens.push(Token { kind, location });
+        }
+
+        Ok(tokens)
+    }
+
+    /// `[Name "value"]`, where the value may escape `"` and `\` with a backslash.
+    fn tag(&mut self, location: PgnLocation) -> Result<TokenKind, ResponseError> {
+        self.advance();
+        self.take_while(char::is_whitespace);
+
+        let name = self.take_while(|character| character.is_ascii_alphanumeric() || character == '_').to_string();
+        if name.is_empty() {
+            return Err(self.error("Tag pair has no name.", self.location()))
+        }
+
+        self.take_while(char::is_whitespace);
+        if self.peek() != Some('"') {
+            return Err(self.error("Tag value must be a quoted string.", self.location()))
+        }
+        self.advance();
+
+        let mut value = String::new();
+        loop {
+            match self.advance() {
+                Some('"') => break,
+                Some('\\') => match self.advance() {
+                    Some(escaped) => value.push(escaped),
+                    None => return Err(self.error("Tag value is never closed.", location)),
+                },
+                Some(character) => value.push(character),
+                None => return Err(self.error("Tag value is never closed.", location)),
+            }
+        }
+
+        self.take_while(char::is_whitespace);
+        if self.peek() != Some(']') {
+            return Err(self.error("Tag pair must end with ].", self.location()))
+        }
+        self.advance();
+
+        Ok(TokenKind::Tag { name, value })
+    }
+
+    fn location(&self) -> PgnLocation {
+        PgnLocation { line: self.line, column: self.column, offset: self.offset }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.text[self.offset..].chars().next()
+    }
+
+    fn advance(&mut self) -> Option<char> {
+        let character = self.peek()?;
+        self.offset += character.len_utf8();
+
+        if character == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+
+        Some(character)
+    }
+
+    fn take_while(&mut self, predicate: impl Fn(char) -> bool) -> &'a str {
+        let start = self.offset;
+
+        while self.peek().is_some_and(&predicate) {
+            self.advance();
+        }
+
+        &self.text[start..self.offset]
+    }
+
+    fn error(&self, message: &str, location: PgnLocation) -> ResponseError {
+        invalid_pgn(message, None, location)
+    }
+}
+
+fn is_symbol_character(character: char) -> bool {
+    character.is_ascii_alphanumeric() || "_+#=:-/".contains(character)
+}
+
+/// Plays `input.moves` from `input.fen` and writes the game in the PGN export format.
+///
+/// The `Result` tag comes from the final position when the game is over. Otherwise it is the
+/// `Result` tag given, such as for a resignation, or `*`. `SetUp` and `FEN` tags are written
+/// for games that do not start from the starting position, and for every Chess960 game along with
+/// a `Variant` tag. Any of these tags given are ignored.
+pub fn export_pgn(input: PgnExportInput) -> Result<String, ResponseError> {
+    let mut position = Position::from_variant_fen(input.variant, input.fen.as_deref().unwrap_or(START_FEN))?;
+    let starting_fen = position.fen();
+    let tags = input.tags.unwrap_or_default();
+    let mut annotations = input.annotations.unwrap_or_default();
+
+    if annotations.len() > input.moves.len() {
+        return Err(ResponseError::invalid_field("annotations", "There are more annotations than moves."))
+    }
+    annotations.resize_with(input.moves.len(), || None);
+
+    for tag in &tags {
+        if tag.name.is_empty() || !tag.name.chars().all(|character| character.is_ascii_alphanumeric() || character == '_') {
+            return Err(ResponseError::invalid_field("tags", format!("{:?} is not a tag name, use letters, digits and _.", tag.name)))
+        }
+        if tag.value.chars().any(char::is_control) {
+            return Err(ResponseError::invalid_field("tags", format!("The value of {} contains a control character.", tag.name)))
+        }
+    }
+
+    let mut words = Vec::new();
+    let mut needs_move_number = true;
+
+    for (index, (notation, annotation)) in input.moves.iter().zip(annotations).enumerate() {
+        let mv = position.parse_move(notation).map_err(|e| match e.code {
+            ErrorCode::IllegalMove => e.with_details(serde_json::json!({ "move": notation, "move_index": index })),
+            _ => e,
+        })?;
+
+        let mover = position.turn();
+        let move_number = position.move_number();
+        let san_move = position.san(mv);
+        position.play(mv);
+
+        match mover {
+            Colour::White => words.push(format!("{}.", move_number)),
+            // Black's moves only need a number at the start and after a comment.
+            Colour::Black if needs_move_number => words.push(format!("{}...", move_number)),
+            Colour::Black => {}
+        }
+        words.push(san_move);
+        needs_move_number = false;
+
+        if let Some(comment) = annotation.map(|annotation| comment_text(annotation, mover)).transpose()?.flatten() {
+            words.extend(format!("{{{}}}", comment).split_whitespace().map(String::from));
+            needs_move_number = true;
+        }
+    }
+
+    let result = match position.game_over() {
+        Some(GameOver::White) => "1-0",
+        Some(GameOver::Black) => "0-1",
+        Some(GameOver::Stalemate) => "1/2-1/2",
+        None => match tags.iter().find(|tag| tag.name == "Result") {
+            Some(tag) if ["1-0", "0-1", "1/2-1/2", "*"].contains(&tag.value.as_str()) => tag.value.as_str(),
+            Some(tag) => return Err(ResponseError::invalid_field("tags", format!("{} is not a game result.", tag.value))),
+            None => "*",
+        },
+    };
+    words.push(result.to_string());
+
+    let mut pgn = String::new();
+
+    for (name, unknown) in SEVEN_TAG_ROSTER {
+        let value = match name {
+            "Result" => result,
+            _ => tags.iter().find(|tag| tag.name == name).map_or(unknown, |tag| tag.value.as_str()),
+        };
+        pgn.push_str(&tag_pair(name, value));
+    }
+
+    for tag in &tags {
+        let derived = SEVEN_TAG_ROSTER.iter().any(|(name, _)| *name == tag.name) || ["SetUp", "FEN", "Variant"].contains(&tag.name.as_str());
+        if !derived {
+            pgn.push_str(&tag_pair(&tag.name, &tag.value));
+        }
+    }
+
+    if input.variant == Variant::Chess960 {
+        pgn.push_str(&tag_pair("Variant", "Chess960"));
+    }
+
+    if starting_fen != START_FEN || input.variant == Variant::Chess960 {
+        pgn.push_str(&tag_pair("SetUp", "1"));
+        pgn.push_str(&tag_pair("FEN", &starting_fen));
+    }
+
+    pgn.push('\n');
+    pgn.push_str(&wrap(&words));
+    pgn.push('\n');
+
+    Ok(pgn)
+}
+
+/// The text of the comment after a move, with the eval in the `[%eval]` command most tools read.
+fn comment_text(annotation: PgnMoveAnnotation, mover: Colour) -> Result<Option<String>, ResponseError> {
+    // `%eval` is from white's point of view, in pawns.
+    let eval = annotation.eval.map(|eval| {
+        let sign = match mover {
+            Colour::White => 1,
+            Colour::Black => -1,
+        };
+
+        match eval {
+            Score::Centipawns(centipawns) => format!("[%eval {:.2}]", (sign * centipawns) as f64 / 100.0),
+            Score::Mate(moves) => format!("[%eval #{}]", sign * moves),
+        }
+    });
+
+    let comment = annotation.comment.filter(|comment| !comment.trim().is_empty());
+    if comment.as_ref().is_some_and(|comment| comment.contains('}')) {
+        return Err(ResponseError::invalid_field("annotations", "Comments cannot contain }."))
+    }
+
+    let text: Vec<String> = eval.into_iter().chain(comment).collect();
+    Ok((!text.is_empty()).then(|| text.join(" ")))
+}
+
+fn tag_pair(name: &str, value: &str) -> String {
+    format!("[{} \"{}\"]\n", name, value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Joins `words` with spaces, breaking lines between words to keep them within `MAX_LINE_LENGTH`.
+fn wrap(words: &[String]) -> String {
+    let mut text = String::new();
+    let mut line_length = 0;
+
+    for word in words {
+        if line_length > 0 && line_length + 1 + word.len() > MAX_LINE_LENGTH {
+            text.push('\n');
+            line_length = 0;
+        } else if line_length > 0 {
+            text.push(' ');
+            line_length += 1;
+        }
+
+        text.push_str(word);
+        line_length += word.len();
+    }
+
+    text
+}
\ No newline at end of file
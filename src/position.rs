@@ -0,0 +1,798 @@
+//! The chess rules everything else is built on. pleco generates the moves and plays them, and
+//! shakmaty validates FENs and reads SAN, but neither is seen outside of this module. Handlers,
+//! the engine and the tactics only use `Position`, `Move`, `Square` and `SquareSet`, so the libraries
+//! behind them can be changed without touching any of those.
+//!
+//! pleco only knows standard chess, so Chess960 positions are played with shakmaty instead.
+
+use std::{fmt, ops::{BitAnd, BitOr, BitXor, Not}, str::FromStr};
+use pleco::{board::perft::perft, BitBoard, BitMove, Board, PieceType, Player, SQ};
+use shakmaty::{
+    attacks,
+    fen::Fen,
+    san::SanPlus,
+    zobrist::{Zobrist64, ZobristHash},
+    Bitboard, CastlingMode, Chess, Color, EnPassantMode, Piece, Position as _, Role,
+};
+
+use crate::types::{Colour, ErrorCode, GameOver, PieceKind, ResponseError, Variant};
+
+pub const START_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+/// Largest move counters accepted in a FEN. pleco overflows on counters a little over
+/// 30000, and no real game gets anywhere near these.
+const MAX_HALFMOVE_CLOCK: u32 = 1000;
+const MAX_FULLMOVE_NUMBER: u32 = 10000;
+
+/// Flags pleco leaves unused in its moves, marking castling in Chess960. pleco cannot play these
+/// moves, but packing them the same way lets them be stored in 16 bits like any other.
+const CHESS960_KING_CASTLE: u16 = 0b0110;
+const CHESS960_QUEEN_CASTLE: u16 = 0b0111;
+
+/// A square of the board, numbered from a1 = 0, b1 = 1... up to h8 = 63.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Square(u8);
+
+impl Square {
+    pub fn new(index: u8) -> Option<Self> {
+        (index < 64).then_some(Self(index))
+    }
+
+    /// Every square, from a1 to h8.
+    pub fn all() -> impl Iterator<Item = Square> {
+        (0..64).map(Square)
+    }
+
+    pub fn index(self) -> u8 {
+        self.0
+    }
+
+    /// 0 for the a-file up to 7 for the h-file.
+    pub fn file(self) -> u8 {
+        self.0 % 8
+    }
+
+    /// 0 for the first rank up to 7 for the eighth.
+    pub fn rank(self) -> u8 {
+        self.0 / 8
+    }
+
+    fn from_sq(sq: SQ) -> Self {
+        Self(sq.0)
+    }
+
+    fn sq(self) -> SQ {
+        SQ(self.0)
+    }
+}
+
+impl fmt::Display for Square {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", (b'a' + self.file()) as char, self.rank() + 1)
+    }
+}
+
+impl FromStr for Square {
+    type Err = ();
+
+    /// Reads a square name such as `e4`.
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.as_bytes() {
+            [file @ b'a'..=b'h', rank @ b'1'..=b'8'] => Ok(Self((rank - b'1') * 8 + (file - b'a'))),
+            _ => Err(()),
+        }
+    }
+}
+
+/// A set of squares, one bit for each in the order of `Square::index`. Iterating it gives the
+/// squares from a1 up to h8.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SquareSet(u64);
+
+impl SquareSet {
+    /// The squares strictly between `a` and `b` when both are on the same rank, file or diagonal,
+    /// otherwise none.
+    pub fn between(a: Square, b: Square) -> Self {
+        Self(attacks::between(shakmaty_square(a), shakmaty_square(b)).0)
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn contains(self, square: Square) -> bool {
+        self.0 & (1 << square.0) != 0
+    }
+}
+
+impl From<Square> for SquareSet {
+    fn from(square: Square) -> Self {
+        Self(1 << square.0)
+    }
+}
+
+impl Iterator for SquareSet {
+    type Item = Square;
+
+    fn next(&mut self) -> Option<Square> {
+        if self.0 == 0 {
+            return None
+        }
+
+        let square = Square(self.0.trailing_zeros() as u8);
+        self.0 &= self.0 - 1;
+        Some(square)
+    }
+}
+
+impl BitAnd for SquareSet {
+    type Output = Self;
+
+    fn bitand(self, other: Self) -> Self {
+        Self(self.0 & other.0)
+    }
+}
+
+impl BitOr for SquareSet {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+}
+
+impl BitXor for SquareSet {
+    type Output = Self;
+
+    fn bitxor(self, other: Self) -> Self {
+        Self(self.0 ^ other.0)
+    }
+}
+
+impl Not for SquareSet {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        Self(!self.0)
+    }
+}
+
+/// A legal move in the position it was generated from. Moves are only meaningful in that position,
+/// so they are found with `Position::legal_moves` or `Position::parse_move` rather than built.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Move(BitMove);
+
+impl Move {
+    /// Stands in for a missing move, such as at a leaf of the search.
+    pub fn null() -> Self {
+        Self(BitMove::null())
+    }
+
+    pub fn is_null(self) -> bool {
+        self.0.is_null()
+    }
+
+    pub fn from(self) -> Square {
+        Square::from_sq(self.0.get_src())
+    }
+
+    /// Where the piece lands. For castling this is the king's square, not the rook's.
+    pub fn to(self) -> Square {
+        match self.castling_rook() {
+            Some((rook_from, _)) => {
+                let file = if rook_from.file() > self.from().file() { 6 } else { 2 };
+                Square(self.from().rank() * 8 + file)
+            }
+            None => Square::from_sq(self.0.get_dest()),
+        }
+    }
+
+    /// The move in UCI, such as `e2e4` or `e7e8q`. Castling is written as the king's move, `e1g1`,
+    /// except in Chess960 where it is the king taking its own rook, `e1h1`.
+    pub fn uci(self) -> String {
+        if self.is_chess960_castle() {
+            format!("{}{}", self.from(), Square::from_sq(self.0.get_dest()))
+        } else {
+            self.0.stringify()
+        }
+    }
+
+    pub fn is_castle(self) -> bool {
+        self.0.is_castle() || self.is_chess960_castle()
+    }
+
+    fn is_chess960_castle(self) -> bool {
+        matches!(self.0.flag(), CHESS960_KING_CASTLE | CHESS960_QUEEN_CASTLE)
+    }
+
+    pub fn is_en_passant(self) -> bool {
+        self.0.is_en_passant()
+    }
+
+    pub fn promotion(self) -> Option<PieceKind> {
+        self.0.is_promo().then(|| piece_kind(self.0.promo_piece())).flatten()
+    }
+
+    /// Where the rook castling moves from and to.
+    pub fn castling_rook(self) -> Option<(Square, Square)> {
+        // Castling is stored as the king taking its own rook.
+        self.is_castle().then(|| {
+            let rook_from = Square::from_sq(self.0.get_dest());
+            let file = if rook_from.file() > self.from().file() { 5 } else { 3 };
+
+            (rook_from, Square(rook_from.rank() * 8 + file))
+        })
+    }
+
+    /// The move packed into 16 bits, for storing in the transposition table.
+    pub fn to_bits(self) -> u16 {
+        self.0.get_raw()
+    }
+
+    pub fn from_bits(bits: u16) -> Self {
+        Self(BitMove::new(bits))
+    }
+}
+
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.uci())
+    }
+}
+
+/// A chess position, along with the moves played to reach it from the FEN it was read from so
+/// they can be taken back.
+#[derive(Clone)]
+pub struct Position {
+    rules: Rules,
+}
+
+/// The library a position is played with. pleco is much faster, which matters to the engine, but
+/// cannot castle in Chess960. shakmaty can, but cannot take a move back or rebuild a position from
+/// an undo record without checking it all over again, so the shakmaty position before each move is kept instead.
+#[derive(Clone)]
+enum Rules {
+    Standard(Board),
+    Chess960 { chess: Chess, history: Vec<Chess> },
+}
+
+impl Position {
+    pub fn start() -> Self {
+        Self { rules: Rules::Standard(Board::start_pos()) }
+    }
+
+    /// Parses a FEN of a standard chess position from a request.
+    pub fn from_fen(fen: &str) -> Result<Self, ResponseError> {
+        Self::from_variant_fen(Variant::Standard, fen)
+    }
+
+    /// Parses a FEN from a request. Chess960 castling rights may be given in Shredder-FEN, as the
+    /// files of the rooks (`HAha`), or in X-FEN, as `KQkq` for the outermost rooks.
+    ///
+    /// pleco panics on some positions it cannot handle, such as a missing king or the side not to move
+    /// being in check, so the position is checked with shakmaty before pleco sees it.
+    pub fn from_variant_fen(variant: Variant, fen: &str) -> Result<Self, ResponseError> {
+        let fen = fen.trim();
+
+        let setup = Fen::from_ascii(fen.as_bytes())
+            .map_err(|e| ResponseError::new(ErrorCode::InvalidFen, e.to_string()))?;
+        let chess: Chess = setup.into_position(castling_mode(variant))
+            .map_err(|e| ResponseError::new(ErrorCode::InvalidFen, e.to_string()))?;
+
+        let fields: Vec<&str> = fen.split_whitespace().collect();
+
+        let halfmoves_valid = fields.get(4)
+            .is_none_or(|halfmoves| halfmoves.parse::<u32>().is_ok_and(|halfmoves| halfmoves <= MAX_HALFMOVE_CLOCK));
+        if !halfmoves_valid {
+            return Err(ResponseError::new(ErrorCode::InvalidFen, format!("halfmove clock must be between 0 and {}", MAX_HALFMOVE_CLOCK)))
+        }
+
+        let fullmoves_valid = fields.get(5)
+            .is_none_or(|fullmoves| fullmoves.parse::<u32>().is_ok_and(|fullmoves| (1..=MAX_FULLMOVE_NUMBER).contains(&fullmoves)));
+        if !fullmoves_valid {
+            return Err(ResponseError::new(ErrorCode::InvalidFen, format!("fullmove number must be between 1 and {}", MAX_FULLMOVE_NUMBER)))
+        }
+
+        if variant == Variant::Chess960 {
+            return Ok(Self { rules: Rules::Chess960 { chess, history: Vec::new() } })
+        }
+
+        // shakmaty also reads Shredder-FEN castling rights such as `HAha`, which pleco panics on.
+        let castling_valid = fields.get(2)
+            .is_none_or(|castling| *castling == "-" || castling.chars().all(|right| "KQkq".contains(right)));
+        if !castling_valid {
+            return Err(ResponseError::new(ErrorCode::InvalidFen, "castling rights must be - or a combination of KQkq, unless the variant is chess960"))
+        }
+
+        // pleco only implements Debug for its FEN errors, and ends each message with a newline.
+        let board = Board::from_fen(fen)
+            .map_err(|e| ResponseError::new(ErrorCode::InvalidFen, format!("{:?}", e).trim_end()))?;
+
+        Ok(Self { rules: Rules::Standard(board) })
+    }
+
+    pub fn variant(&self) -> Variant {
+        match &self.rules {
+            Rules::Standard(_) => Variant::Standard,
+            Rules::Chess960 { .. } => Variant::Chess960,
+        }
+    }
+
+    /// The FEN of the position. Chess960 castling rights are written in X-FEN, with the file of the
+    /// rook only when it is not the outermost one.
+    pub fn fen(&self) -> String {
+        match &self.rules {
+            Rules::Standard(board) => board.fen(),
+            // pleco writes the en passant square whenever a pawn could capture there.
+            Rules::Chess960 { chess, .. } => Fen::from_position(chess, EnPassantMode::PseudoLegal).to_string(),
+        }
+    }
+
+    pub fn turn(&self) -> Colour {
+        match &self.rules {
+            Rules::Standard(board) => colour(board.turn()),
+            Rules::Chess960 { chess, .. } => colour_of(chess.turn()),
+        }
+    }
+
+    /// The number of the move about to be played, counting a move of each side as one.
+    pub fn move_number(&self) -> u32 {
+        match &self.rules {
+            Rules::Standard(board) => board.moves_played() as u32 / 2 + 1,
+            Rules::Chess960 { chess, .. } => chess.fullmoves().get(),
+        }
+    }
+
+    /// Identifies the position, for the transposition table. Equal positions have equal hashes.
+    pub fn hash(&self) -> u64 {
+        match &self.rules {
+            Rules::Standard(board) => board.zobrist(),
+            Rules::Chess960 { chess, .. } => chess.zobrist_hash::<Zobrist64>(EnPassantMode::Legal).0,
+        }
+    }
+
+    pub fn legal_moves(&self) -> Vec<Move> {
+        match &self.rules {
+            Rules::Standard(board) => board.generate_moves().iter().map(|&mv| Move(mv)).collect(),
+            Rules::Chess960 { chess, .. } => chess.legal_moves().iter().map(|&mv| chess960_move(mv)).collect(),
+        }
+    }
+
+    /// Plays a legal move.
+    pub fn play(&mut self, mv: Move) {
+        match &mut self.rules {
+            Rules::Standard(board) => board.apply_move(mv.0),
+            Rules::Chess960 { chess, history } => {
+                let mv = shakmaty_move(chess, mv);
+                history.push(chess.clone());
+                chess.play_unchecked(mv);
+            }
+        }
+    }
+
+    /// Takes back the last move played.
+    pub fn undo(&mut self) {
+        match &mut self.rules {
+            Rules::Standard(board) => board.undo_move(),
+            Rules::Chess960 { chess, history } => {
+                if let Some(previous) = history.pop() {
+                    *chess = previous;
+                }
+            }
+        }
+    }
+
+    pub fn in_check(&self) -> bool {
+        match &self.rules {
+            Rules::Standard(board) => board.in_check(),
+            Rules::Chess960 { chess, .. } => chess.is_check(),
+        }
+    }
+
+    pub fn is_checkmate(&self) -> bool {
+        match &self.rules {
+            Rules::Standard(board) => board.checkmate(),
+            Rules::Chess960 { chess, .. } => chess.is_checkmate(),
+        }
+    }
+
+    /// How the game has ended, if it has. pleco's `Board::stalemate` also counts 50 plies without a
+    /// capture or pawn move as a stalemate, so a stalemate is checked for here as no legal moves out of check.
+    pub fn game_over(&self) -> Option<GameOver> {
+        let has_moves = match &self.rules {
+            Rules::Standard(board) => !board.generate_moves().is_empty(),
+            Rules::Chess960 { chess, .. } => !chess.legal_moves().is_empty(),
+        };
+        if has_moves {
+            return None
+        }
+        if !self.in_check() {
+            return Some(GameOver::Stalemate)
+        }
+        match self.turn() {
+            Colour::White => Some(GameOver::Black),
+            Colour::Black => Some(GameOver::White),
+        }
+    }
+
+    pub fn piece_at(&self, square: Square) -> Option<(Colour, PieceKind)> {
+        match &self.rules {
+            Rules::Standard(board) => {
+                let (player, piece) = board.piece_at_sq(square.sq()).player_piece()?;
+
+                Some((colour(player), piece_kind(piece)?))
+            }
+            Rules::Chess960 { chess, .. } => {
+                let piece = chess.board().piece_at(shakmaty_square(square))?;
+
+                Some((colour_of(piece.color), kind_of(piece.role)))
+            }
+        }
+    }
+
+    /// The squares of every piece on the board.
+    pub fn occupied(&self) -> SquareSet {
+        match &self.rules {
+            Rules::Standard(board) => SquareSet(board.occupied().0),
+            Rules::Chess960 { chess, .. } => SquareSet(chess.board().occupied().0),
+        }
+    }
+
+    /// The squares of the pieces of `colour`.
+    pub fn occupied_by(&self, colour: Colour) -> SquareSet {
+        match &self.rules {
+            Rules::Standard(board) => SquareSet(board.get_occupied_player(player(colour)).0),
+            Rules::Chess960 { chess, .. } => SquareSet(chess.board().by_color(color(colour)).0),
+        }
+    }
+
+    /// The pieces of either side attacking `square`, as if the only pieces on the board were
+    /// those on `occupied`. Taking pieces out of `occupied` lets the pieces behind them attack
+    /// through, as they would once the pieces in front have moved or been captured.
+    pub fn attackers_to(&self, square: Square, occupied: SquareSet) -> SquareSet {
+        let attackers = match &self.rules {
+            Rules::Standard(board) => board.attackers_to(square.sq(), BitBoard(occupied.0)).0,
+            Rules::Chess960 { chess, .. } => {
+                let (square, occupied) = (shakmaty_square(square), Bitboard(occupied.0));
+                (chess.board().attacks_to(square, Color::White, occupied) | chess.board().attacks_to(square, Color::Black, occupied)).0
+            }
+        };
+
+        SquareSet(attackers) & occupied
+    }
+
+    /// The squares the piece on `square` attacks, none if the square is empty.
+    pub fn attacks_from(&self, square: Square) -> SquareSet {
+        let Some((colour, piece)) = self.piece_at(square) else {
+            return SquareSet::default()
+        };
+
+        let piece = Piece { color: color(colour), role: role(piece) };
+        SquareSet(attacks::attacks(shakmaty_square(square), piece, Bitboard(self.occupied().0)).0)
+    }
+
+    pub fn king_square(&self, colour: Colour) -> Square {
+        match &self.rules {
+            Rules::Standard(board) => Square::from_sq(board.king_sq(player(colour))),
+            Rules::Chess960 { chess, .. } => {
+                let king = chess.board().king_of(color(colour)).expect("shakmaty only reads positions with both kings");
+                Square(king as u8)
+            }
+        }
+    }
+
+    /// The piece `mv` moves. The king when castling.
+    pub fn moved_piece(&self, mv: Move) -> Option<PieceKind> {
+        match &self.rules {
+            Rules::Standard(board) => piece_kind(board.moved_piece(mv.0).type_of()),
+            Rules::Chess960 { chess, .. } => chess.board().role_at(shakmaty_square(mv.from())).map(kind_of),
+        }
+    }
+
+    pub fn is_capture(&self, mv: Move) -> bool {
+        match &self.rules {
+            Rules::Standard(board) => board.is_capture(mv.0),
+            Rules::Chess960 { chess, .. } => shakmaty_move(chess, mv).is_capture(),
+        }
+    }
+
+    pub fn captured_piece(&self, mv: Move) -> Option<PieceKind> {
+        match &self.rules {
+            Rules::Standard(board) => if board.is_capture(mv.0) { piece_kind(board.captured_piece(mv.0)) } else { None },
+            Rules::Chess960 { chess, .. } => shakmaty_move(chess, mv).capture().map(kind_of),
+        }
+    }
+
+    /// The legal move written in UCI as `uci_move`, if there is one.
+    pub fn uci_move(&self, uci_move: &str) -> Option<Move> {
+        self.legal_moves().into_iter().find(|mv| mv.uci() == uci_move)
+    }
+
+    /// Finds the legal move `notation` describes, in either UCI (`e2e4`, `e7e8q`) or SAN (`e4`, `Nxf7+`, `O-O`).
+    pub fn parse_move(&self, notation: &str) -> Result<Move, ResponseError> {
+        let notation = notation.trim();
+
+        if let Some(mv) = self.uci_move(&notation.to_ascii_lowercase()) {
+            return Ok(mv)
+        }
+
+        let illegal_move = || {
+            ResponseError::new(ErrorCode::IllegalMove, format!("{} is not a legal move in {}", notation, self.fen()))
+                .with_details(serde_json::json!({ "move": notation }))
+        };
+
+        let san = SanPlus::from_ascii(notation.as_bytes()).map_err(|_| illegal_move())?.san;
+        let position = self.shakmaty_position()?;
+        let uci_move = san.to_move(&position).map_err(|_| illegal_move())?
+            .to_uci(castling_mode(self.variant()))
+            .to_string();
+
+        self.uci_move(&uci_move)
+            .ok_or_else(|| ResponseError::internal(format!("pleco has no move {} in {}", uci_move, self.fen())))
+    }
+
+    /// Writes a legal move in SAN, such as `Nbd7`, `exd6`, `e8=Q+` or `O-O#`. The piece is only
+    /// disambiguated by its file, rank or square when another piece of the same kind can also move
+    /// to the same square.
+    pub fn san(&self, mv: Move) -> String {
+        let board = match &self.rules {
+            Rules::Standard(board) => board,
+            Rules::Chess960 { chess, .. } => return SanPlus::from_move(chess.clone(), shakmaty_move(chess, mv)).to_string(),
+        };
+
+        let mut san = String::new();
+
+        match mv.castling_rook() {
+            Some((rook_from, _)) if rook_from.file() > mv.from().file() => san.push_str("O-O"),
+            Some(_) => san.push_str("O-O-O"),
+            None => {
+                let piece = self.moved_piece(mv);
+                let capture = self.is_capture(mv);
+
+                match piece {
+                    Some(PieceKind::Pawn) | None => {
+                        if capture {
+                            san.push((b'a' + mv.from().file()) as char);
+                        }
+                    }
+                    Some(piece) => {
+                        san.push(piece_letter(piece));
+                        san.push_str(&self.disambiguation(board, mv, piece));
+                    }
+                }
+
+                if capture {
+                    san.push('x');
+                }
+                san.push_str(&mv.to().to_string());
+
+                if let Some(promotion) = mv.promotion() {
+                    san.push('=');
+                    san.push(piece_letter(promotion));
+                }
+            }
+        }
+
+        if board.gives_check(mv.0) {
+            let mut after = board.clone();
+            after.apply_move(mv.0);
+            san.push(if after.generate_moves().is_empty() { '#' } else { '+' });
+        }
+
+        san
+    }
+
+    /// Writes a line of moves played one after the other from this position in SAN.
+    pub fn san_line(&self, line: &[Move]) -> Vec<String> {
+        let mut position = self.clone();
+
+        line.iter()
+            .map(|&mv| {
+                let san = position.san(mv);
+                position.play(mv);
+                san
+            })
+            .collect()
+    }
+
+    /// What tells `mv` apart from the moves of the other pieces of the same kind to the same square:
+    /// nothing if there are none, the file if that is enough, then the rank, then both.
+    fn disambiguation(&self, board: &Board, mv: Move, piece: PieceKind) -> String {
+        // Most moves have no rival, and checking the attacks is far cheaper than generating moves.
+        let rivals = board.attackers_to(mv.to().sq(), board.occupied())
+            & board.piece_bb(board.turn(), piece_type(piece))
+            & !mv.from().sq().to_bb();
+        if rivals.is_empty() {
+            return String::new()
+        }
+
+        let others: Vec<Square> = self.legal_moves().into_iter()
+            .filter(|other| other.from() != mv.from() && other.to() == mv.to() && !other.is_castle())
+            .filter(|&other| self.moved_piece(other) == Some(piece))
+            .map(Move::from)
+            .collect();
+
+        let from = mv.from().to_string();
+        if others.is_empty() {
+            String::new()
+        } else if others.iter().all(|other| other.file() != mv.from().file()) {
+            from[..1].to_string()
+        } else if others.iter().all(|other| other.rank() != mv.from().rank()) {
+            from[1..].to_string()
+        } else {
+            from
+        }
+    }
+
+    /// Counts the positions exactly `depth` plies ahead.
+    pub fn perft(&self, depth: u16) -> u64 {
+        match &self.rules {
+            Rules::Standard(board) => perft(board, depth),
+            Rules::Chess960 { chess, .. } => shakmaty::perft(chess, depth as u32),
+        }
+    }
+
+    /// Reads the position into shakmaty.
+    fn shakmaty_position(&self) -> Result<Chess, ResponseError> {
+        let fen = match &self.rules {
+            Rules::Standard(board) => board.fen(),
+            Rules::Chess960 { chess, .. } => return Ok(chess.clone()),
+        };
+        let fen_obj = Fen::from_ascii(fen.as_bytes())
+            .map_err(|e| ResponseError::internal(format!("Could not read {} back into shakmaty: {}", fen, e)))?;
+
+        fen_obj.into_position(CastlingMode::Standard)
+            .map_err(|e| ResponseError::internal(format!("shakmaty rejected {}: {}", fen, e)))
+    }
+}
+
+fn castling_mode(variant: Variant) -> CastlingMode {
+    match variant {
+        Variant::Standard => CastlingMode::Standard,
+        Variant::Chess960 => CastlingMode::Chess960,
+    }
+}
+
+/// A move shakmaty generated in a Chess960 position, packed the way pleco packs its moves.
+fn chess960_move(mv: shakmaty::Move) -> Move {
+    let (flag, from, to) = match mv {
+        shakmaty::Move::Normal { role, from, capture, to, promotion } => {
+            let flag = match promotion {
+                // The promotion flags go knight, bishop, rook, queen, and then the same again for captures.
+                Some(promotion) => {
+                    let piece = match promotion {
+                        Role::Knight => 0,
+                        Role::Bishop => 1,
+                        Role::Rook => 2,
+                        _ => 3,
+                    };
+                    BitMove::FLAG_PROMO_N + piece + if capture.is_some() { 4 } else { 0 }
+                }
+                None if capture.is_some() => BitMove::FLAG_CAPTURE,
+                None if role == Role::Pawn && (from as u8).abs_diff(to as u8) == 16 => BitMove::FLAG_DOUBLE_PAWN,
+                None => BitMove::FLAG_QUIET,
+            };
+            (flag, from, to)
+        }
+        shakmaty::Move::EnPassant { from, to } => (BitMove::FLAG_EP, from, to),
+        shakmaty::Move::Castle { king, rook } if rook > king => (CHESS960_KING_CASTLE, king, rook),
+        shakmaty::Move::Castle { king, rook } => (CHESS960_QUEEN_CASTLE, king, rook),
+        shakmaty::Move::Put { .. } => unreachable!("there are no drops in chess"),
+    };
+
+    Move(BitMove::make(flag, SQ(from as u8), SQ(to as u8)))
+}
+
+/// The shakmaty move for `mv` in `chess`, the position it was generated in.
+fn shakmaty_move(chess: &Chess, mv: Move) -> shakmaty::Move {
+    let from = shakmaty_square(mv.from());
+    let to = shakmaty::Square::new(mv.0.get_dest_u8() as u32);
+
+    if mv.is_castle() {
+        shakmaty::Move::Castle { king: from, rook: to }
+    } else if mv.is_en_passant() {
+        shakmaty::Move::EnPassant { from, to }
+    } else {
+        shakmaty::Move::Normal {
+            role: chess.board().role_at(from).unwrap_or(Role::Pawn),
+            from,
+            capture: chess.board().role_at(to),
+            to,
+            promotion: mv.promotion().map(role),
+        }
+    }
+}
+
+fn shakmaty_square(square: Square) -> shakmaty::Square {
+    shakmaty::Square::new(square.0 as u32)
+}
+
+/// Whether `notation` is written like a move in SAN, legal or not.
+pub fn is_san(notation: &str) -> bool {
+    SanPlus::from_ascii(notation.as_bytes()).is_ok()
+}
+
+fn piece_letter(piece: PieceKind) -> char {
+    match piece {
+        PieceKind::Pawn => 'P',
+        PieceKind::Knight => 'N',
+        PieceKind::Bishop => 'B',
+        PieceKind::Rook => 'R',
+        PieceKind::Queen => 'Q',
+        PieceKind::King => 'K',
+    }
+}
+
+/// The kind of a real piece. `None` for pleco's `PieceType::None` and `PieceType::All`.
+fn piece_kind(piece_type: PieceType) -> Option<PieceKind> {
+    match piece_type {
+        PieceType::P => Some(PieceKind::Pawn),
+        PieceType::N => Some(PieceKind::Knight),
+        PieceType::B => Some(PieceKind::Bishop),
+        PieceType::R => Some(PieceKind::Rook),
+        PieceType::Q => Some(PieceKind::Queen),
+        PieceType::K => Some(PieceKind::King),
+        PieceType::None | PieceType::All => None,
+    }
+}
+
+fn piece_type(piece: PieceKind) -> PieceType {
+    match piece {
+        PieceKind::Pawn => PieceType::P,
+        PieceKind::Knight => PieceType::N,
+        PieceKind::Bishop => PieceType::B,
+        PieceKind::Rook => PieceType::R,
+        PieceKind::Queen => PieceType::Q,
+        PieceKind::King => PieceType::K,
+    }
+}
+
+fn colour(player: Player) -> Colour {
+    match player {
+        Player::White => Colour::White,
+        Player::Black => Colour::Black,
+    }
+}
+
+fn player(colour: Colour) -> Player {
+    match colour {
+        Colour::White => Player::White,
+        Colour::Black => Player::Black,
+    }
+}
+
+fn colour_of(color: Color) -> Colour {
+    match color {
+        Color::White => Colour::White,
+        Color::Black => Colour::Black,
+    }
+}
+
+fn color(colour: Colour) -> Color {
+    match colour {
+        Colour::White => Color::White,
+        Colour::Black => Color::Black,
+    }
+}
+
+fn kind_of(role: Role) -> PieceKind {
+    match role {
+        Role::Pawn => PieceKind::Pawn,
+        Role::Knight => PieceKind::Knight,
+        Role::Bishop => PieceKind::Bishop,
+        Role::Rook => PieceKind::Rook,
+        Role::Queen => PieceKind::Queen,
+        Role::King => PieceKind::King,
+    }
+}
+
+fn role(piece: PieceKind) -> Role {
+    match piece {
+        PieceKind::Pawn => Role::Pawn,
+        PieceKind::Knight => Role::Knight,
+        PieceKind::Bishop => Role::Bishop,
+        PieceKind::Rook => Role::Rook,
+        PieceKind::Queen => Role::Queen,
+        PieceKind::King => Role::King,
+    }
+}
\ No newline at end of file
@@ -0,0 +1,214 @@
+use std::{future::Future, pin::Pin, sync::LazyLock, time::SystemTime};
+use lambda_http::{http::{header, Method, StatusCode}, Body, Error, Request, RequestExt, Response};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::error::Category;
+
+use crate::{cors::CorsConfig, handlers, types::{ApplyMoveInput, BestMoveInput, ErrorCode, FenInput, HealthResponse, HintInput, PerftInput, PgnExportInput, PgnInput, ResponseError, ReviewInput}};
+
+type HandlerFuture = Pin<Box<dyn Future<Output = Result<Response<Body>, Error>> + Send>>;
+type BoxedHandler = Box<dyn Fn(Request) -> HandlerFuture + Send + Sync>;
+
+static ROUTER: LazyLock<Router> = LazyLock::new(|| Router::new(vec![
+    Route::new(Method::GET, "/health_check", |_: (), _| async {
+        Ok::<HealthResponse, ResponseError>(handlers::health_check::health_check().await)
+    }),
+    Route::new(Method::POST, "/best_move", |input: BestMoveInput, context| {
+        handlers::best_move::best_move(input, context.deadline)
+    }),
+    Route::new(Method::POST, "/legal_moves", |input: FenInput, _| {
+        handlers::legal_moves::legal_moves(input.fen, input.variant)
+    }),
+    Route::new(Method::POST, "/validate_fen", |input: FenInput, _| {
+        handlers::validate_fen::validate_fen(input.fen, input.variant)
+    }),
+    Route::new(Method::POST, "/apply_move", |input: ApplyMoveInput, _| {
+        handlers::apply_move::apply_move(input)
+    }),
+    Route::new(Method::POST, "/pgn/parse", |input: PgnInput, _| {
+        handlers::pgn_parse::pgn_parse(input.pgn)
+    }),
+    Route::new(Method::POST, "/pgn/export", |input: PgnExportInput, _| {
+        handlers::pgn_export::pgn_export(input)
+    }),
+    Route::new(Method::POST, "/hint", |input: HintInput, context| {
+        handlers::hint::hint(input, context.deadline)
+    }),
+    Route::new(Method::POST, "/review", |input: ReviewInput, context| {
+        handlers::review::review(input, context.deadline)
+    }),
+    Route::new(Method::POST, "/threats", |input: FenInput, _| {
+        handlers::threats::threats(input.fen, input.variant)
+    }),
+    Route::new(Method::POST, "/perft", |input: PerftInput, context| {
+        handlers::perft::perft(input, context.deadline)
+    }),
+]).with_cors(CorsConfig::from_env()));
+
+/// Routes a request to its handler. Shared by the Lambda function and the local server.
+pub async fn handler(req: Request) -> Result<Response<Body>, Error> {
+    ROUTER.handle(req).await
+}
+
+/// What a handler may need to know about the request besides its body.
+#[derive(Debug, Clone, Copy)]
+pub struct RequestContext {
+    /// When the Lambda invocation times out. `None` when not running on Lambda.
+    pub deadline: Option<SystemTime>,
+}
+
+impl RequestContext {
+    fn from_request(req: &Request) -> Self {
+        let deadline = req.lambda_context_ref()
+            .filter(|context| context.deadline > 0)
+            .map(|context| context.deadline());
+
+        Self { deadline }
+    }
+}
+
+/// A single endpoint. The handler's argument is the request body type and its `Ok` value is the
+/// response body type, both as JSON. `Ok` becomes a 200 and `Err` the status of its error code.
+pub struct Route {
+    method: Method,
+    path: &'static str,
+    handler: BoxedHandler,
+}
+
+impl Route {
+    pub fn new<I, O, F, Fut>(method: Method, path: &'static str, handler: F) -> Self
+    where
+        I: DeserializeOwned,
+        O: Serialize,
+        F: Fn(I, RequestContext) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<O, ResponseError>> + Send + 'static,
+    {
+        // A GET has no meaningful body, so one sent anyway (as some health probes do) is ignored.
+        let ignores_body = method == Method::GET;
+
+        let handler: BoxedHandler = Box::new(move |req: Request| {
+            let context = RequestContext::from_request(&req);
+            let body = if ignores_body { &[] } else { req.body().as_ref() };
+            let result = parse_body::<I>(body).map(|input| handler(input, context));
+
+            Box::pin(async move {
+                match result {
+                    Ok(result) => match result.await {
+                        Ok(response) => json_response(StatusCode::OK, &response),
+                        Err(response_error) => error_response(&response_error),
+                    },
+                    Err(response_error) => error_response(&response_error),
+                }
+            })
+        });
+
+        Self { method, path, handler }
+    }
+}
+
+pub struct Router {
+    routes: Vec<Route>,
+    cors: Option<CorsConfig>,
+}
+
+impl Router {
+    pub fn new(routes: Vec<Route>) -> Self {
+        Self { routes, cors: None }
+    }
+
+    pub fn with_cors(mut self, cors: CorsConfig) -> Self {
+        self.cors = Some(cors);
+        self
+    }
+
+    /// Responds 404 if no route has the path, and 405 if routes have the path but not the method.
+    /// `OPTIONS` is answered for every known path, as browsers send it before cross-origin requests.
+    pub async fn handle(&self, req: Request) -> Result<Response<Body>, Error> {
+        let request_headers = req.headers().clone();
+
+        let path_routes: Vec<&Route> = self.routes.iter()
+            .filter(|route| route.path == req.uri().path())
+            .collect();
+
+        let mut allowed_methods: Vec<&str> = path_routes.iter().map(|route| route.method.as_str()).collect();
+        allowed_methods.push(Method::OPTIONS.as_str());
+        let allow = allowed_methods.join(", ");
+
+        let mut response = if path_routes.is_empty() {
+            let message = format!("No route for {}.", req.uri().path());
+            error_response(&ResponseError::new(ErrorCode::NotFound, message))?
+        } else if req.method() == Method::OPTIONS {
+            let mut response = Response::builder()
+                .status(StatusCode::NO_CONTENT)
+                .header(header::ALLOW, &allow)
+                .body(Body::Empty)?;
+
+            if let Some(cors) = &self.cors {
+                cors.apply_preflight(&request_headers, response.headers_mut(), &allow);
+            }
+
+            response
+        } else {
+            match path_routes.iter().find(|route| route.method == req.method()) {
+                Some(route) => (route.handler)(req).await?,
+                None => {
+                    let response_error = ResponseError::new(
+                        ErrorCode::MethodNotAllowed,
+                        format!("{} does not accept {}.", req.uri().path(), req.method()),
+                    ).with_details(serde_json::json!({ "allow": allowed_methods }));
+
+                    let mut response = error_response(&response_error)?;
+                    response.headers_mut().insert(header::ALLOW, allow.parse()?);
+                    response
+                }
+            }
+        };
+
+        if let Some(cors) = &self.cors {
+            cors.apply(&request_headers, response.headers_mut());
+        }
+
+        Ok(response)
+    }
+}
+
+/// An empty body is read as JSON `null`, so endpoints without a body can take `()`.
+/// Bodies that are not JSON are `INVALID_JSON`, JSON of the wrong shape is `INVALID_REQUEST`.
+fn parse_body<I: DeserializeOwned>(body: &[u8]) -> Result<I, ResponseError> {
+    let body = if body.is_empty() { b"null".as_slice() } else { body };
+
+    serde_json::from_slice(body).map_err(|e| {
+        let code = match e.classify() {
+            Category::Data => ErrorCode::InvalidRequest,
+            Category::Io | Category::Syntax | Category::Eof => ErrorCode::InvalidJson,
+        };
+
+        ResponseError::new(code, format!("Invalid request body: {}", e))
+            .with_details(serde_json::json!({ "line": e.line(), "column": e.column() }))
+    })
+}
+
+fn status(code: ErrorCode) -> StatusCode {
+    match code {
+        ErrorCode::NotFound => StatusCode::NOT_FOUND,
+        ErrorCode::MethodNotAllowed => StatusCode::METHOD_NOT_ALLOWED,
+        ErrorCode::InvalidJson
+        | ErrorCode::InvalidRequest
+        | ErrorCode::InvalidFen
+        | ErrorCode::IllegalMove
+        | ErrorCode::InvalidPgn
+        | ErrorCode::NoLegalMoves => StatusCode::BAD_REQUEST,
+        ErrorCode::Timeout => StatusCode::SERVICE_UNAVAILABLE,
+        ErrorCode::InternalError => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+fn error_response(response_error: &ResponseError) -> Result<Response<Body>, Error> {
+    json_response(status(response_error.code), response_error)
+}
+
+fn json_response<T: Serialize>(status: StatusCode, body: &T) -> Result<Response<Body>, Error> {
+    Ok(Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(serde_json::to_string(body)?.into())?)
+}
\ No newline at end of file
@@ -0,0 +1,301 @@
+//! Static tactics found from the attacks on the board, through `Position`'s attack queries.
+
+use crate::{
+    bot::heuristics::piece_value,
+    position::{Move, Position, Square, SquareSet},
+    types::{Colour, PieceKind},
+};
+
+/// Attackers are tried from the least valuable up, the order an exchange is played in.
+const EXCHANGE_ORDER: [PieceKind; 6] = [PieceKind::Pawn, PieceKind::Knight, PieceKind::Bishop, PieceKind::Rook, PieceKind::Queen, PieceKind::King];
+
+/// Static exchange evaluation: the material the side of the piece on `from` wins by moving it to
+/// `to` and letting both sides keep recapturing there with their least valuable piece, each side
+/// stopping as soon as carrying on would lose material. Pieces behind the attackers (x-rays) join
+/// in once the pieces in front of them have captured.
+///
+/// Pins and checks are ignored, as is usual for a static exchange.
+pub fn static_exchange(position: &Position, from: Square, to: Square) -> i32 {
+    let Some((mut side, piece)) = position.piece_at(from) else { return 0 };
+
+    let mut gain = [0; 32];
+    let mut depth = 0;
+    let mut occupied = position.occupied();
+    let mut attacker = (from, piece);
+
+    gain[0] = value(position, to);
+
+    loop {
+        depth += 1;
+        side = !side;
+
+        // What the exchange is worth so far if the piece that just captured is taken in turn.
+        gain[depth] = piece_value(attacker.1) - gain[depth - 1];
+
+        // Neither side can do better by carrying on.
+        if (-gain[depth - 1]).max(gain[depth]) < 0 || depth == gain.len() - 1 {
+            break;
+        }
+
+        occupied = occupied ^ SquareSet::from(attacker.0);
+        let attackers = position.attackers_to(to, occupied);
+
+        match least_valuable_attacker(position, attackers, side) {
+            // The king cannot capture onto a square the other side still attacks.
+            Some((_, PieceKind::King)) if !(attackers & position.occupied_by(!side)).is_empty() => break,
+            Some(next_attacker) => attacker = next_attacker,
+            None => break,
+        }
+    }
+
+    // Walk back from the end of the exchange, letting each side stop when it is ahead.
+    while depth > 1 {
+        depth -= 1;
+        gain[depth - 1] = -(-gain[depth - 1]).max(gain[depth]);
+    }
+
+    gain[0]
+}
+
+/// The material value of the piece on `square`, 0 if it is empty.
+fn value(position: &Position, square: Square) -> i32 {
+    position.piece_at(square).map_or(0, |(_, piece)| piece_value(piece))
+}
+
+/// The least valuable piece of `side` among `attackers`, and its square.
+fn least_valuable_attacker(position: &Position, attackers: SquareSet, side: Colour) -> Option<(Square, PieceKind)> {
+    let attackers = attackers & position.occupied_by(side);
+
+    EXCHANGE_ORDER.iter().find_map(|&piece| {
+        attackers
+            .into_iter()
+            .find(|&square| position.piece_at(square) == Some((side, piece)))
+            .map(|square| (square, piece))
+    })
+}
+
+/// The squares of the pieces of `side` attacking `square`.
+fn attackers_of(position: &Position, square: Square, side: Colour) -> SquareSet {
+    position.attackers_to(square, position.occupied()) & position.occupied_by(side)
+}
+
+/// What the other side wins by capturing the piece on `square` with its least valuable attacker,
+/// or `None` if it cannot win material there. Kings are never hanging.
+pub fn hanging_value(position: &Position, square: Square) -> Option<i32> {
+    let (side, piece) = position.piece_at(square)?;
+    if piece == PieceKind::King {
+        return None
+    }
+
+    let (attacker, _) = least_valuable_attacker(position, attackers_of(position, square, !side), !side)?;
+    let value = static_exchange(position, attacker, square);
+
+    (value > 0).then_some(value)
+}
+
+/// The pieces of `side` the other side could win material by capturing, with what it would win.
+pub fn hanging_pieces(position: &Position, side: Colour) -> Vec<(Square, i32)> {
+    position.occupied_by(side)
+        .filter_map(|square| hanging_value(position, square).map(|value| (square, value)))
+        .collect()
+}
+
+/// The pieces of the other side attacking `square` that are worth less than the piece on it.
+/// Attacks on the king are checks, and are left out.
+pub fn lower_valued_attackers(position: &Position, square: Square) -> Vec<Square> {
+    let Some((side, piece)) = position.piece_at(square) else { return Vec::new() };
+    if piece == PieceKind::King {
+        return Vec::new()
+    }
+
+    attackers_of(position, square, !side)
+        .filter(|&attacker| value(position, attacker) < piece_value(piece))
+        .collect()
+}
+
+/// The pieces of `side` pinned to their king, each with the piece pinning it.
+pub fn pins(position: &Position, side: Colour) -> Vec<(Square, Square)> {
+    let king = position.king_square(side);
+
+    position.occupied_by(side)
+        .filter(|&pinned| pinned != king)
+        .filter_map(|pinned| {
+            let pinner = revealed_attackers(position, king, pinned) & position.occupied_by(!side);
+            pinner.into_iter().next().map(|pinner| (pinned, pinner))
+        })
+        .collect()
+}
+
+/// The discovered attacks the side to move can make: a piece that can move out of the way of a
+/// piece of its own behind it, which then attacks a piece of the other side worth attacking.
+/// Each is the piece to move, the piece behind it, and the piece attacked.
+pub fn discovered_attacks(position: &Position) -> Vec<(Square, Square, Square)> {
+    let side = position.turn();
+    let moves = position.legal_moves();
+    let mut attacks = Vec::new();
+
+    for target in position.occupied_by(!side) {
+        for blocker in position.occupied_by(side) {
+            for attacker in revealed_attackers(position, target, blocker) & position.occupied_by(side) {
+                let line = SquareSet::between(attacker, target) | SquareSet::from(target);
+                let uncovers = moves.iter()
+                    .any(|mv| mv.from() == blocker && !line.contains(mv.to()));
+
+                if uncovers && position.piece_at(attacker).is_some_and(|(_, piece)| worth_attacking(position, target, piece)) {
+                    attacks.push((blocker, attacker, target));
+                }
+            }
+        }
+    }
+
+    attacks
+}
+
+/// The moves of the side to move that attack two or more pieces worth attacking at once, with the
+/// squares of those pieces. Moves that leave the forking piece to be won are left out.
+pub fn forks(position: &Position) -> Vec<(Move, Vec<Square>)> {
+    let side = position.turn();
+
+    position.legal_moves().into_iter()
+        .filter(|mv| !mv.is_castle())
+        .filter_map(|mv| {
+            let mut after = position.clone();
+            after.play(mv);
+
+            if hanging_value(&after, mv.to()).is_some() {
+                return None
+            }
+
+            // A promoted pawn forks as the piece it promoted to.
+            let (_, piece) = after.piece_at(mv.to())?;
+            let targets: Vec<Square> = (after.attacks_from(mv.to()) & after.occupied_by(!side))
+                .filter(|&target| worth_attacking(&after, target, piece))
+                .collect();
+
+            (targets.len() >= 2).then_some((mv, targets))
+        })
+        .collect()
+}
+
+/// The position with `side` to move, as if the other side had passed. `None` if that position is
+/// illegal, which it is when the other side is in check.
+pub fn with_side_to_move(position: &Position, side: Colour) -> Option<Position> {
+    if position.turn() == side {
+        return Some(position.clone())
+    }
+
+    let fen = position.fen();
+    let mut fields: Vec<&str> = fen.split_whitespace().collect();
+    fields[1] = if fields[1] == "w" { "b" } else { "w" };
+    // An en passant capture is only possible straight after the pawn's move.
+    fields[3] = "-";
+
+    Position::from_variant_fen(position.variant(), &fields.join(" ")).ok()
+}
+
+/// The pieces of either side that attack `square` once `blocker` is taken off the board, and did
+/// not before.
+fn revealed_attackers(position: &Position, square: Square, blocker: Square) -> SquareSet {
+    let occupied = position.occupied();
+    let without_blocker = occupied ^ SquareSet::from(blocker);
+
+    position.attackers_to(square, without_blocker) & !position.attackers_to(square, occupied)
+}
+
+/// Whether attacking the piece on `target` with a `piece` threatens anything: the king, a piece
+/// worth more, or a piece nothing defends.
+fn worth_attacking(position: &Position, target: Square, piece: PieceKind) -> bool {
+    let Some((side, target_piece)) = position.piece_at(target) else { return false };
+
+    target_piece == PieceKind::King
+        || piece_value(target_piece) > piece_value(piece)
+        || attackers_of(position, target, side).is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{position::{Position, Square}, types::{Colour, Variant}};
+    use super::{discovered_attacks, forks, hanging_pieces, pins, static_exchange, with_side_to_move};
+
+    fn square(name: &str) -> Square {
+        name.parse().unwrap()
+    }
+
+    fn see(fen: &str, from: &str, to: &str) -> i32 {
+        static_exchange(&Position::from_fen(fen).unwrap(), square(from), square(to))
+    }
+
+    #[test]
+    fn static_exchange_plays_out_recaptures() {
+        // An undefended pawn.
+        assert_eq!(see("1k1r4/1pp4p/p7/4p3/8/P5P1/1PP4P/2K1R3 w - - 0 1", "e1", "e5"), 100);
+        // A defended pawn taken by a knight loses the knight for it.
+        assert_eq!(see("1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1", "d3", "e5"), -220);
+        // The queen behind the rook recaptures through it.
+        assert_eq!(see("4k3/8/8/3p4/8/8/3R4/3Q2K1 w - - 0 1", "d2", "d5"), 100);
+        // Against a battery of its own the defence comes out ahead.
+        assert_eq!(see("3qk3/3r4/8/3p4/8/8/3R4/3Q2K1 w - - 0 1", "d2", "d5"), -400);
+        // A quiet move to a safe square costs nothing, the pawn only attacks c4 and e4.
+        assert_eq!(see("4k3/8/8/3p4/8/8/8/2B3K1 w - - 0 1", "c1", "e3"), 0);
+        // A quiet move onto a square the other side attacks loses the piece.
+        assert_eq!(see("4k3/8/8/3p4/8/8/8/2B3K1 w - - 0 1", "c1", "c4"), -330);
+    }
+
+    #[test]
+    fn static_exchange_keeps_the_king_out_of_defended_squares() {
+        // The king would recapture on d2 but the rook behind defends it.
+        assert_eq!(see("3rk3/8/8/8/8/8/3P4/4K3 b - - 0 1", "d8", "d2"), -400);
+        assert_eq!(see("4k3/8/8/8/8/8/3PK3/r7 b - - 0 1", "a1", "a2"), 0);
+    }
+
+    #[test]
+    fn finds_hanging_pieces() {
+        let board = Position::from_fen("4k3/8/2n5/8/3P4/5N2/8/4K3 w - - 0 1").unwrap();
+
+        assert_eq!(hanging_pieces(&board, Colour::White), Vec::new());
+        assert_eq!(hanging_pieces(&board, Colour::Black), Vec::new());
+
+        // The pawn on d4 is attacked twice and defended once.
+        let board = Position::from_fen("4k3/8/2n5/4p3/3P4/5N2/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(hanging_pieces(&board, Colour::White), vec![(square("d4"), 100)]);
+    }
+
+    #[test]
+    fn finds_pins_and_discovered_attacks() {
+        let board = Position::from_fen("4k3/8/2n5/1B6/8/8/4N3/4R1K1 w - - 0 1").unwrap();
+
+        assert_eq!(pins(&board, Colour::Black), vec![(square("c6"), square("b5"))]);
+        assert_eq!(pins(&board, Colour::White), Vec::new());
+        assert_eq!(discovered_attacks(&board), vec![(square("e2"), square("e1"), square("e8"))]);
+
+        // Black cannot move the pinned knight, and has no pieces behind its own.
+        let passed = with_side_to_move(&board, Colour::Black).unwrap();
+        assert_eq!(discovered_attacks(&passed), Vec::new());
+    }
+
+    #[test]
+    fn chess960_positions_have_the_same_tactics_as_standard_ones() {
+        // Castling rights only change the moves, never the attacks.
+        let chess960 = Position::from_variant_fen(Variant::Chess960, "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9").unwrap();
+        let standard = Position::from_fen("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w - - 2 9").unwrap();
+
+        for colour in [Colour::White, Colour::Black] {
+            assert_eq!(hanging_pieces(&chess960, colour), hanging_pieces(&standard, colour));
+            assert_eq!(pins(&chess960, colour), pins(&standard, colour));
+        }
+        assert_eq!(discovered_attacks(&chess960), discovered_attacks(&standard));
+        assert_eq!(forks(&chess960), forks(&standard));
+    }
+
+    #[test]
+    fn finds_forks_that_keep_the_piece() {
+        let board = Position::from_fen("r3k3/8/8/3N4/8/8/8/4K3 w - - 0 1").unwrap();
+        let found: Vec<_> = forks(&board).into_iter().map(|(mv, targets)| (mv.uci(), targets)).collect();
+
+        assert_eq!(found, vec![(String::from("d5c7"), vec![square("a8"), square("e8")])]);
+
+        // The knight on c7 would be taken by the queen.
+        let board = Position::from_fen("r3k3/8/3q4/3N4/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(forks(&board), Vec::new());
+    }
+}
\ No newline at end of file
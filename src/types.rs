@@ -1,18 +1,18 @@
-use std::{cmp::Ordering, u8};
-use pleco::{BitMove, Board};
+use std::{cmp::Ordering, ops::Not, time::Duration};
 use serde::{Deserialize, Serialize};
+use crate::position::{Move, Position};
 
 /// Represents an evaluation score along with its height in the game tree.
 /// Height refers to the number of levels a node is from the deepest leaf.
 /// 
 /// - The first `i32` is the evaluation score.
 /// - The second `i32` is the height of the node in the search tree.
-/// - The `BitMove` is the best move to make after consideration.
+/// - The `Move` is the best move to make from the node, or null at a leaf.
 #[derive(Debug, Eq, Clone, Copy, PartialEq)]
 pub struct MoveGenerationData {
     pub evaluation: i32,
     pub height: u8,
-    pub bit_move: BitMove
+    pub mv: Move
 }
 
 impl MoveGenerationData {
@@ -20,7 +20,7 @@ impl MoveGenerationData {
         Self {
             evaluation: i32::MIN,
             height: u8::MAX,
-            bit_move: BitMove::null(),
+            mv: Move::null(),
         }
     }
 
@@ -28,7 +28,7 @@ impl MoveGenerationData {
         Self {
             evaluation: i32::MAX,
             height: 0,
-            bit_move: BitMove::null(),
+            mv: Move::null(),
         }
     }
 }
@@ -51,9 +51,68 @@ impl PartialOrd for MoveGenerationData {
 }
 
 pub struct BestMove {
-    pub uci_move: String,
-    pub san_move: String,
-    pub resulting_board: Board,
+    /// The move to play. A weakened bot may not play the first move of its best line.
+    pub chosen_move: Move,
+    pub resulting_position: Position,
+    pub lines: Vec<SearchLine>,
+    pub seed: u64,
+    /// The search was stopped before reaching its full depth.
+    pub truncated: bool,
+}
+
+/// A principal variation from the root, as found by the engine.
+pub struct SearchLine {
+    pub score: Score,
+    pub depth: u8,
+    pub pv: Vec<Move>,
+}
+
+/// Progress of a running search, reported after every completed depth.
+pub struct SearchReport<'a> {
+    pub depth: u8,
+    pub nodes: u64,
+    pub elapsed: Duration,
+    pub lines: &'a [SearchLine],
+}
+
+/// An evaluation from the point of view of the side to move.
+/// `Mate` counts moves until checkmate, negative if the side to move is getting mated.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Score {
+    Centipawns(i32),
+    Mate(i32),
+}
+
+/// Winning mates are best (quickest first), then centipawns, then losing mates (slowest first).
+impl Ord for Score {
+    fn cmp(&self, other: &Self) -> Ordering {
+        fn rank(score: &Score) -> (i32, i32) {
+            match *score {
+                Score::Mate(moves) if moves > 0 => (2, -moves),
+                Score::Centipawns(centipawns) => (1, centipawns),
+                Score::Mate(moves) => (0, -moves),
+            }
+        }
+
+        rank(self).cmp(&rank(other))
+    }
+}
+
+impl PartialOrd for Score {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// The rules a position is played by. In Chess960 the pieces on the back ranks start in any of 960
+/// arrangements, and castling moves the king and rook to the squares they reach in standard chess.
+/// Requests give it as `standard` or `chess960`, and play by the standard rules when it is left out.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Variant {
+    #[default]
+    Standard,
+    Chess960,
 }
 
 #[derive(Serialize)]
@@ -63,12 +122,68 @@ pub enum GameOver {
     Stalemate,
 }
 
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Colour {
+    White,
+    Black,
+}
+
+impl Not for Colour {
+    type Output = Colour;
+
+    fn not(self) -> Colour {
+        match self {
+            Colour::White => Colour::Black,
+            Colour::Black => Colour::White,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PieceKind {
+    Pawn,
+    Knight,
+    Bishop,
+    Rook,
+    Queen,
+    King,
+}
+
 #[derive(Serialize)]
 pub struct ResultingGameState {
     pub uci_move: String,
     pub san_move: String,
     pub resulting_fen: String,
     pub game_over: Option<GameOver>,
+    /// The piece that moves. The king when castling.
+    pub piece: PieceKind,
+    pub from: String,
+    /// Where the piece lands. For castling this is the king's square, not the rook's.
+    pub to: String,
+    pub captured_piece: Option<PieceKind>,
+    pub promotion: Option<PieceKind>,
+    /// The side to move in the resulting position is in check.
+    pub is_check: bool,
+    pub is_castle: bool,
+    /// How the rook moves when castling.
+    pub castling_rook: Option<RookMove>,
+    pub is_en_passant: bool,
+}
+
+#[derive(Serialize)]
+pub struct RookMove {
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Serialize)]
+pub struct CandidateMove {
+    pub uci_move: String,
+    pub san_move: String,
+    pub score: Score,
+    pub depth: u8,
+    pub pv: Vec<String>,
+    pub san_pv: Vec<String>,
 }
 
 #[derive(Serialize)]
@@ -77,7 +192,224 @@ pub struct BestMoveResponse {
     pub uci_move: String,
     pub san_move: String,
     pub resulting_fen: String,
-    pub resulting_legal_moves: Vec<ResultingGameState>
+    pub resulting_legal_moves: Vec<ResultingGameState>,
+    pub candidate_moves: Vec<CandidateMove>,
+    pub seed: u64,
+    pub truncated: bool,
+}
+
+#[derive(Serialize)]
+pub struct PgnParseResponse {
+    pub games: Vec<PgnGame>,
+}
+
+#[derive(Serialize)]
+pub struct PgnGame {
+    /// In the order they appear in the PGN.
+    pub tags: Vec<PgnTag>,
+    /// `chess960` if the `Variant` tag names Chess960.
+    pub variant: Variant,
+    /// From the `FEN` tag, or the starting position.
+    pub starting_fen: String,
+    /// Comments before the first move.
+    pub comments: Vec<String>,
+    pub moves: Vec<PgnPly>,
+    /// `1-0`, `0-1`, `1/2-1/2` or `*`, if the game ends with one.
+    pub result: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PgnTag {
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(Serialize)]
+pub struct PgnPly {
+    /// Counts moves by either side from the start of the game, starting at 1.
+    pub ply: usize,
+    /// The full move number, as written before white's moves in PGN.
+    pub move_number: u32,
+    pub uci_move: String,
+    pub san_move: String,
+    pub resulting_fen: String,
+    pub comments: Vec<String>,
+    /// Numeric annotation glyphs, with `!`, `?`, `!!`, `??`, `!?` and `?!` read as 1 to 6.
+    pub nags: Vec<u8>,
+    /// Alternatives to this move, each starting from the position before it.
+    pub variations: Vec<Vec<PgnPly>>,
+}
+
+#[derive(Serialize)]
+pub struct PgnExportResponse {
+    pub pgn: String,
+}
+
+#[derive(Serialize)]
+pub struct ReviewResponse {
+    pub starting_fen: String,
+    pub moves: Vec<ReviewedMove>,
+    pub white: ReviewSummary,
+    pub black: ReviewSummary,
+}
+
+/// A played move compared with the engine's best move in the same position.
+/// Both evaluations are from the point of view of the side that played the move.
+#[derive(Serialize)]
+pub struct ReviewedMove {
+    pub ply: usize,
+    pub move_number: u32,
+    pub colour: Colour,
+    pub uci_move: String,
+    pub san_move: String,
+    pub eval: Score,
+    pub best_uci_move: String,
+    pub best_san_move: String,
+    pub best_eval: Score,
+    pub centipawn_loss: u32,
+    pub classification: MoveClassification,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoveClassification {
+    Best,
+    Good,
+    Inaccuracy,
+    Mistake,
+    Blunder,
+}
+
+#[derive(Serialize)]
+pub struct ReviewSummary {
+    pub moves: usize,
+    /// From 0 to 100. `None` if the side played no moves.
+    pub accuracy: Option<f64>,
+    pub average_centipawn_loss: Option<u32>,
+    pub inaccuracies: usize,
+    pub mistakes: usize,
+    pub blunders: usize,
+}
+
+#[derive(Serialize)]
+pub struct HintResponse {
+    pub uci_move: String,
+    pub san_move: String,
+    /// From the point of view of the side to move.
+    pub score: Score,
+    pub pv: Vec<String>,
+    pub san_pv: Vec<String>,
+    /// Why the move is good, most important first.
+    pub reasons: Vec<HintReason>,
+}
+
+#[derive(Serialize)]
+pub struct HintReason {
+    #[serde(flatten)]
+    pub kind: HintReasonKind,
+    /// A short English description, generated from the fields of `kind`.
+    pub text: String,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "reason")]
+pub enum HintReasonKind {
+    Checkmate,
+    /// The search found a forced mate.
+    ForcesMate { moves: i32 },
+    /// The static exchange evaluation of the capture, in centipawns.
+    WinsMaterial { see: i32 },
+    Promotes { piece: PieceKind },
+    /// Mate would follow if the other side ignored the move.
+    ThreatensMate { moves: i32 },
+    /// The moved piece could be won where it stood, and cannot where it goes.
+    SavesPiece { piece: PieceKind, from: String, to: String },
+    /// Another piece could be won before the move, and cannot after it.
+    DefendsPiece { piece: PieceKind, square: String },
+    GivesCheck,
+    /// The king's piece-square term of the evaluation goes up.
+    ImprovesKingSafety { before: i32, after: i32 },
+    /// The moved piece's piece-square term of the evaluation goes up by `gain`.
+    ImprovesPiece { piece: PieceKind, gain: i32 },
+}
+
+#[derive(Serialize)]
+pub struct ThreatsResponse {
+    pub side_to_move: SideThreats,
+    pub opponent: SideThreats,
+}
+
+/// What threatens one side's pieces, and the tactics it has against the other side.
+#[derive(Serialize)]
+pub struct SideThreats {
+    pub colour: Colour,
+    /// Pieces the other side wins material by capturing.
+    pub hanging: Vec<HangingPiece>,
+    /// Pieces attacked by a piece worth less.
+    pub attacked_by_lower: Vec<AttackedPiece>,
+    /// Pieces pinned to their king.
+    pub pinned: Vec<PinnedPiece>,
+    pub discovered_attacks: Vec<DiscoveredAttack>,
+    pub forks: Vec<Fork>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct PlacedPiece {
+    pub piece: PieceKind,
+    pub square: String,
+}
+
+#[derive(Serialize)]
+pub struct HangingPiece {
+    pub piece: PieceKind,
+    pub square: String,
+    /// The static exchange evaluation of capturing it, in centipawns.
+    pub value: i32,
+}
+
+#[derive(Serialize)]
+pub struct AttackedPiece {
+    pub piece: PieceKind,
+    pub square: String,
+    pub attackers: Vec<PlacedPiece>,
+}
+
+#[derive(Serialize)]
+pub struct PinnedPiece {
+    pub piece: PieceKind,
+    pub square: String,
+    pub pinned_by: PlacedPiece,
+}
+
+/// Moving `piece` out of the way lets `attacker` behind it attack `target`.
+#[derive(Serialize)]
+pub struct DiscoveredAttack {
+    pub piece: PieceKind,
+    pub square: String,
+    pub attacker: PlacedPiece,
+    pub target: PlacedPiece,
+}
+
+#[derive(Serialize)]
+pub struct Fork {
+    pub uci_move: String,
+    pub san_move: String,
+    pub piece: PieceKind,
+    pub targets: Vec<PlacedPiece>,
+}
+
+#[derive(Serialize)]
+pub struct PerftResponse {
+    /// Leaf nodes of the legal move tree.
+    pub nodes: u64,
+    /// The leaf nodes after each legal move, sorted by `uci_move`.
+    pub divide: Vec<PerftMove>,
+}
+
+#[derive(Serialize)]
+pub struct PerftMove {
+    pub uci_move: String,
+    pub san_move: String,
+    pub nodes: u64,
 }
 
 #[derive(Serialize)]
@@ -94,15 +426,152 @@ pub struct LegalMoves {
 #[derive(Serialize)]
 pub struct ValidateFenResponse {
     pub valid: bool,
-    pub error: Option<String>,
+    pub error: Option<ResponseError>,
 }
 
-#[derive(Serialize)]
+/// Stable, machine readable reason a request failed. Clients should match on this rather than the message.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    InvalidJson,
+    InvalidRequest,
+    InvalidFen,
+    IllegalMove,
+    InvalidPgn,
+    NoLegalMoves,
+    NotFound,
+    MethodNotAllowed,
+    Timeout,
+    InternalError,
+}
+
+/// The body of every error response.
+#[derive(Serialize, Debug)]
 pub struct ResponseError {
-    pub error: String,
+    pub code: ErrorCode,
+    pub message: String,
+    pub details: Option<serde_json::Value>,
+}
+
+impl ResponseError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self { code, message: message.into(), details: None }
+    }
+
+    pub fn with_details(mut self, details: serde_json::Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+
+    /// Something went wrong on our side, such as pleco and shakmaty disagreeing about a position.
+    pub fn internal(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::InternalError, message)
+    }
+
+    /// A request field with a value outside of what it accepts.
+    pub fn invalid_field(field: &str, message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::InvalidRequest, message).with_details(serde_json::json!({ "field": field }))
+    }
 }
 
 #[derive(Deserialize)]
 pub struct FenInput {
     pub fen: String,
+    #[serde(default)]
+    pub variant: Variant,
+}
+
+#[derive(Deserialize)]
+pub struct BestMoveInput {
+    pub fen: String,
+    #[serde(default)]
+    pub variant: Variant,
+    pub multipv: Option<usize>,
+    pub skill_level: Option<u8>,
+    pub elo: Option<u32>,
+    pub nodes: Option<u64>,
+    pub seed: Option<u64>,
+    pub clock: Option<Clock>,
+}
+
+#[derive(Deserialize)]
+pub struct HintInput {
+    pub fen: String,
+    #[serde(default)]
+    pub variant: Variant,
+    pub nodes: Option<u64>,
+}
+
+#[derive(Deserialize)]
+pub struct PerftInput {
+    pub fen: String,
+    #[serde(default)]
+    pub variant: Variant,
+    /// Plies to count to.
+    pub depth: u8,
+}
+
+#[derive(Deserialize)]
+pub struct PgnInput {
+    pub pgn: String,
+}
+
+#[derive(Deserialize)]
+pub struct PgnExportInput {
+    /// Defaults to the starting position.
+    pub fen: Option<String>,
+    #[serde(default)]
+    pub variant: Variant,
+    /// Moves played from `fen`, in UCI or SAN.
+    pub moves: Vec<String>,
+    /// Written after the Seven Tag Roster, in this order.
+    pub tags: Option<Vec<PgnTag>>,
+    /// Annotations for `moves`, by index. May be shorter than `moves`.
+    pub annotations: Option<Vec<Option<PgnMoveAnnotation>>>,
+}
+
+#[derive(Deserialize)]
+pub struct PgnMoveAnnotation {
+    pub comment: Option<String>,
+    /// From the point of view of the side that made the move, as `best_move` reports it.
+    pub eval: Option<Score>,
+}
+
+#[derive(Deserialize)]
+pub struct ReviewInput {
+    /// A single game, reviewed along its main line. Cannot be combined with `fen` and `moves`.
+    pub pgn: Option<String>,
+    /// Defaults to the starting position.
+    pub fen: Option<String>,
+    /// `chess960` to play `fen` and `moves` by the rules of Chess960. A PGN gives its variant in a
+    /// `Variant` tag instead.
+    #[serde(default)]
+    pub variant: Variant,
+    /// Moves played from `fen`, in UCI or SAN.
+    pub moves: Option<Vec<String>>,
+    /// The total time for every search, in milliseconds.
+    pub time_budget_ms: Option<u64>,
+}
+
+#[derive(Deserialize)]
+pub struct ApplyMoveInput {
+    /// Defaults to the starting position.
+    pub fen: Option<String>,
+    #[serde(default)]
+    pub variant: Variant,
+    /// Moves already played from `fen`, in UCI or SAN.
+    pub history: Option<Vec<String>>,
+    /// The move to apply after `history`, in UCI or SAN.
+    #[serde(rename = "move")]
+    pub chess_move: String,
+}
+
+/// The state of the game clock, all times in milliseconds.
+#[derive(Deserialize)]
+pub struct Clock {
+    pub white_time: u64,
+    pub black_time: u64,
+    pub white_increment: Option<u64>,
+    pub black_increment: Option<u64>,
+    pub moves_to_go: Option<u32>,
 }
\ No newline at end of file
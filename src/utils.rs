@@ -1,47 +1,39 @@
-use pleco::{Board, Player};
-use shakmaty::{Chess, fen::Fen, san::San, uci::UciMove, CastlingMode};
+use crate::{position::{Move, Position}, types::{ResponseError, ResultingGameState, RookMove}};
 
-use crate::types::{GameOver, ResultingGameState};
-
-pub fn game_over(board: &Board) -> Option<GameOver> {
-    if board.stalemate() {
-        return Some(GameOver::Stalemate)
-    }
-    if board.checkmate() {
-        return match board.turn() {
-            Player::White => Some(GameOver::Black),
-            Player::Black => Some(GameOver::White)
-        }
-    }
-    None
-}
-
-pub fn get_resulting_game_states(board: &mut Board) -> Vec<ResultingGameState> {
-    let mut moves: Vec<ResultingGameState> = Vec::new();
-
-    let legal_moves = board.generate_moves();
-
-    for mv in legal_moves.iter() {
-        let uci_move: String = mv.stringify();
-        let san_move: String = uci_to_san(&board.fen(), &uci_move);
-
-        board.apply_move(*mv);
-        moves.push(ResultingGameState {
-            uci_move,
-            san_move,
-            resulting_fen: board.fen(),
-            game_over: game_over(board)
-        });
-        board.undo_move();  
-    }
-
-    moves
+pub fn get_resulting_game_states(position: &mut Position) -> Result<Vec<ResultingGameState>, ResponseError> {
+    position.legal_moves().into_iter()
+        .map(|mv| resulting_game_state(position, mv))
+        .collect()
 }
 
-pub fn uci_to_san(fen: &str, uci_move: &str) -> String {
-    let fen_obj = Fen::from_ascii(fen.as_bytes()).unwrap();
-    let position: Chess = fen_obj.into_position(CastlingMode::Standard).unwrap();
-    let uci = UciMove::from_ascii(uci_move.as_bytes()).unwrap();
-    let mv = uci.to_move(&position).unwrap();
-    San::from_move(&position, mv).to_string()
+/// Describes `mv` and the position it leads to. `position` is left as it was.
+pub fn resulting_game_state(position: &mut Position, mv: Move) -> Result<ResultingGameState, ResponseError> {
+    let uci_move = mv.uci();
+    let san_move = position.san(mv);
+
+    let piece = position.moved_piece(mv)
+        .ok_or_else(|| ResponseError::internal(format!("There is no piece to move for {} in {}", uci_move, position.fen())))?;
+    let captured_piece = position.captured_piece(mv);
+    let castling_rook = mv.castling_rook()
+        .map(|(from, to)| RookMove { from: from.to_string(), to: to.to_string() });
+
+    position.play(mv);
+    let resulting_game_state = ResultingGameState {
+        from: mv.from().to_string(),
+        to: mv.to().to_string(),
+        uci_move,
+        san_move,
+        resulting_fen: position.fen(),
+        game_over: position.game_over(),
+        piece,
+        captured_piece,
+        promotion: mv.promotion(),
+        is_check: position.in_check(),
+        is_castle: mv.is_castle(),
+        castling_rook,
+        is_en_passant: mv.is_en_passant(),
+    };
+    position.undo();
+
+    Ok(resulting_game_state)
 }
\ No newline at end of file
@@ -0,0 +1,31 @@
+//! Sends requests through the router, as the Lambda function and the local server do.
+
+// Each suite is its own crate and uses only some of these.
+#![allow(dead_code)]
+
+use lambda_http::{http::Method, Body, Request, Response};
+use serde_json::Value;
+use chess_rest_api_rust_pleco::router::handler;
+
+/// A request with `body` as its raw body, which need not be JSON.
+pub fn request(method: Method, path: &str, body: impl ToString) -> Request {
+    lambda_http::http::Request::builder()
+        .method(method)
+        .uri(path)
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
+
+/// Sends a request and returns the status and JSON body of the response.
+pub async fn send(method: Method, path: &str, body: impl ToString) -> (u16, Value) {
+    read(handler(request(method, path, body)).await.unwrap())
+}
+
+/// The status and JSON body of a response. Panics if the body is not JSON.
+pub fn read(response: Response<Body>) -> (u16, Value) {
+    let status = response.status().as_u16();
+    let body = serde_json::from_slice(response.body())
+        .unwrap_or_else(|e| panic!("replied {} without JSON: {}", status, e));
+
+    (status, body)
+}
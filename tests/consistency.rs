@@ -0,0 +1,275 @@
+//! Checks that `Position`, which generates moves with pleco and writes its own SAN, agrees with
+//! shakmaty, which validates FENs. Random games and perft trees are walked with `Position`, and at
+//! every position shakmaty must read its FEN, write it back the same, and agree on the legal moves,
+//! the SAN of each, check and whether the game is over, as `Position::game_over` reports it.
+//!
+//! A disagreement is reported as the shortest line of moves that reaches one, and the position
+//! shrunk to the fewest pieces that still disagree.
+
+use std::{collections::BTreeSet, fmt, mem};
+use shakmaty::{fen::Fen, san::SanPlus, uci::UciMove, CastlingMode, Chess, EnPassantMode, FromSetup, Position as _, PositionError, Setup, Square};
+use chess_rest_api_rust_pleco::{bot::skill::SkillRng, position::{Move, Position}, types::GameOver};
+
+/// The standard perft positions, from https://www.chessprogramming.org/Perft_Results.
+const PERFT_POSITIONS: [(&str, u16); 6] = [
+    ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", 3),
+    ("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", 2),
+    ("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", 3),
+    ("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1", 3),
+    ("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8", 2),
+    ("r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10", 2),
+];
+
+const RANDOM_GAMES: u64 = 100;
+
+/// Long enough for most random games to reach an ending, and with it promotions and mates.
+const MAX_RANDOM_GAME_PLIES: usize = 300;
+
+/// How the libraries disagree about a position.
+enum Mismatch {
+    /// shakmaty rejects the FEN pleco wrote.
+    Rejected { error: String },
+    Fen { pleco: String, shakmaty: String },
+    Moves { only_pleco: Vec<String>, only_shakmaty: Vec<String> },
+    San { uci_move: String, ours: String, shakmaty: String },
+    Check { pleco: bool, shakmaty: bool },
+    GameEnd { pleco: &'static str, shakmaty: &'static str },
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Mismatch::Rejected { error } => write!(f, "shakmaty rejects pleco's FEN: {}", error),
+            Mismatch::Fen { pleco, shakmaty } => write!(f, "pleco writes {} but shakmaty {}", pleco, shakmaty),
+            Mismatch::Moves { only_pleco, only_shakmaty } => {
+                write!(f, "moves only pleco generates: [{}], only shakmaty: [{}]", only_pleco.join(" "), only_shakmaty.join(" "))
+            }
+            Mismatch::San { uci_move, ours, shakmaty } => write!(f, "{} is written {} but shakmaty writes {}", uci_move, ours, shakmaty),
+            Mismatch::Check { pleco, shakmaty } => write!(f, "in check for pleco: {}, for shakmaty: {}", pleco, shakmaty),
+            Mismatch::GameEnd { pleco, shakmaty } => write!(f, "the game is {} for pleco but {} for shakmaty", pleco, shakmaty),
+        }
+    }
+}
+
+/// A position the libraries disagree about, and how it was reached.
+struct Counterexample {
+    start: String,
+    line: Vec<String>,
+    fen: String,
+    mismatch: Mismatch,
+    /// The position with as many pieces taken off as possible while it still disagrees.
+    shrunk: Option<(String, Mismatch)>,
+}
+
+impl fmt::Display for Counterexample {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "pleco and shakmaty disagree about {}", self.fen)?;
+        writeln!(f, "  reached from {} by [{}]", self.start, self.line.join(" "))?;
+        writeln!(f, "  {}", self.mismatch)?;
+
+        match &self.shrunk {
+            Some((fen, mismatch)) => write!(f, "  shrunk to {}\n  {}", fen, mismatch),
+            None => write!(f, "  no smaller position disagrees the same way"),
+        }
+    }
+}
+
+/// How pleco and shakmaty disagree about `position`, if they do. Only the SAN of `san_moves` is
+/// compared, as writing it for every move of every position makes the random games slow.
+fn compare(position: &Position, san_moves: &[Move]) -> Option<Mismatch> {
+    let fen = position.fen();
+    let chess: Chess = match fen.parse::<Fen>() {
+        Ok(setup) => match setup.into_position(CastlingMode::Standard) {
+            Ok(chess) => chess,
+            Err(e) => return Some(Mismatch::Rejected { error: e.to_string() }),
+        },
+        Err(e) => return Some(Mismatch::Rejected { error: e.to_string() }),
+    };
+
+    // pleco writes the en passant square whenever a pawn could capture there, even if the capture
+    // would leave its king in check. That is valid FEN too, so shakmaty writes it the same way here.
+    let shakmaty_fen = Fen::from_position(&chess, EnPassantMode::PseudoLegal).to_string();
+    if fen != shakmaty_fen {
+        return Some(Mismatch::Fen { pleco: fen, shakmaty: shakmaty_fen })
+    }
+
+    let pleco_moves: BTreeSet<String> = position.legal_moves().iter().map(|mv| mv.uci()).collect();
+    let shakmaty_moves: BTreeSet<String> = chess.legal_moves().iter()
+        .map(|mv| mv.to_uci(CastlingMode::Standard).to_string())
+        .collect();
+    if pleco_moves != shakmaty_moves {
+        return Some(Mismatch::Moves {
+            only_pleco: pleco_moves.difference(&shakmaty_moves).cloned().collect(),
+            only_shakmaty: shakmaty_moves.difference(&pleco_moves).cloned().collect(),
+        })
+    }
+
+    for &mv in san_moves {
+        let uci_move = mv.uci();
+        let shakmaty_move = UciMove::from_ascii(uci_move.as_bytes()).ok().and_then(|uci| uci.to_move(&chess).ok());
+        let Some(shakmaty_move) = shakmaty_move else {
+            // The moves agreed above, so shakmaty reading the move differently is a disagreement too.
+            return Some(Mismatch::Moves { only_pleco: vec![uci_move], only_shakmaty: Vec::new() })
+        };
+        let (ours, shakmaty) = (position.san(mv), SanPlus::from_move(chess.clone(), shakmaty_move).to_string());
+
+        if ours != shakmaty {
+            return Some(Mismatch::San { uci_move, ours, shakmaty })
+        }
+    }
+
+    if position.in_check() != chess.is_check() {
+        return Some(Mismatch::Check { pleco: position.in_check(), shakmaty: chess.is_check() })
+    }
+
+    let pleco_end = match position.game_over() {
+        Some(GameOver::Stalemate) => "stalemate",
+        Some(_) => "checkmate",
+        None => "ongoing",
+    };
+    let shakmaty_end = match (chess.is_checkmate(), chess.is_stalemate()) {
+        (true, _) => "checkmate",
+        (false, true) => "stalemate",
+        (false, false) => "ongoing",
+    };
+    if pleco_end != shakmaty_end {
+        return Some(Mismatch::GameEnd { pleco: pleco_end, shakmaty: shakmaty_end })
+    }
+
+    None
+}
+
+fn counterexample(start: &str, line: &[String], position: &Position, mismatch: Mismatch) -> Box<Counterexample> {
+    Box::new(Counterexample {
+        start: start.to_string(),
+        line: line.to_vec(),
+        fen: position.fen(),
+        shrunk: shrink(&position.fen(), |smaller| {
+            compare(smaller, &smaller.legal_moves()).filter(|found| mem::discriminant(found) == mem::discriminant(&mismatch))
+        }),
+        mismatch,
+    })
+}
+
+/// Takes pieces other than the kings off the board one at a time, keeping each removal after which
+/// `disagrees` still finds a disagreement, until no more can be taken off. `None` if no piece can be.
+fn shrink<T>(fen: &str, disagrees: impl Fn(&Position) -> Option<T>) -> Option<(String, T)> {
+    let mut smallest = None;
+    let mut current = fen.to_string();
+
+    'shrinking: loop {
+        let setup = current.parse::<Fen>().ok()?.into_setup();
+
+        for square in setup.board.occupied() {
+            if setup.board.kings().contains(square) {
+                continue;
+            }
+
+            let Some(smaller) = without_piece(setup.clone(), square) else { continue };
+            let Ok(position) = Position::from_fen(&smaller) else { continue };
+
+            if let Some(found) = disagrees(&position) {
+                current = smaller.clone();
+                smallest = Some((smaller, found));
+                continue 'shrinking;
+            }
+        }
+
+        return smallest
+    }
+}
+
+/// The FEN of `setup` without the piece on `square`, dropping castling rights and the en passant
+/// square if they stop being valid. `None` if the position is illegal without it.
+fn without_piece(mut setup: Setup, square: Square) -> Option<String> {
+    setup.board.discard_piece_at(square);
+
+    let chess: Chess = Chess::from_setup(setup, CastlingMode::Standard)
+        .or_else(PositionError::ignore_invalid_castling_rights)
+        .or_else(PositionError::ignore_invalid_ep_square)
+        .ok()?;
+
+    Some(Fen::from_position(&chess, EnPassantMode::Legal).to_string())
+}
+
+/// Plays a random legal move at every ply, comparing every position and move on the way.
+fn random_game(seed: u64) -> Result<(), Box<Counterexample>> {
+    let start = PERFT_POSITIONS[0].0;
+    let mut rng = SkillRng::new(seed);
+    let mut position = Position::from_fen(start).unwrap();
+    let mut line = Vec::new();
+
+    for _ in 0..MAX_RANDOM_GAME_PLIES {
+        let moves = position.legal_moves();
+        let mv = (!moves.is_empty()).then(|| moves[(rng.next_u64() % moves.len() as u64) as usize]);
+
+        if let Some(mismatch) = compare(&position, mv.as_slice()) {
+            return Err(counterexample(start, &line, &position, mismatch))
+        }
+
+        let Some(mv) = mv else { break };
+        line.push(mv.uci());
+        position.play(mv);
+    }
+
+    Ok(())
+}
+
+/// Compares every position exactly `depth` plies into the perft tree, depth first. Comparing the
+/// depths in order means the first disagreement found is one of the closest to the root.
+/// `compare_san` compares the SAN of every legal move from those positions too.
+fn perft_tree(start: &str, position: &mut Position, line: &mut Vec<String>, depth: u16, compare_san: bool) -> Result<(), Box<Counterexample>> {
+    if depth == 0 {
+        let san_moves = if compare_san { position.legal_moves() } else { Vec::new() };
+
+        return match compare(position, &san_moves) {
+            Some(mismatch) => Err(counterexample(start, line, position, mismatch)),
+            None => Ok(()),
+        }
+    }
+
+    for mv in position.legal_moves() {
+        line.push(mv.uci());
+        position.play(mv);
+
+        let result = perft_tree(start, position, line, depth - 1, compare_san);
+
+        position.undo();
+        line.pop();
+        result?;
+    }
+
+    Ok(())
+}
+
+#[test]
+fn random_games_agree() {
+    for seed in 0..RANDOM_GAMES {
+        if let Err(counterexample) = random_game(seed) {
+            panic!("random game {}: {}", seed, counterexample);
+        }
+    }
+}
+
+#[test]
+fn perft_trees_agree() {
+    for (fen, max_depth) in PERFT_POSITIONS {
+        let mut position = Position::from_fen(fen).unwrap();
+
+        // The SAN of every move in the tree is compared from the positions one ply above the leaves.
+        for depth in 0..=max_depth {
+            if let Err(counterexample) = perft_tree(fen, &mut position, &mut Vec::new(), depth, depth < max_depth) {
+                panic!("perft depth {}: {}", depth, counterexample);
+            }
+        }
+    }
+}
+
+#[test]
+fn shrinks_to_the_pieces_that_matter() {
+    // Being in check stands in for a disagreement, and needs only the kings and the checking rook.
+    let shrunk = shrink("r3k3/pppq4/8/8/8/8/3PPPP1/4K2r w - - 0 1", |position| position.in_check().then_some(()));
+
+    assert_eq!(shrunk.map(|(fen, _)| fen).as_deref(), Some("4k3/8/8/8/8/8/8/4K2r w - - 0 1"));
+    assert!(shrink("4k3/8/8/8/8/8/8/4K2r w - - 0 1", |position| position.in_check().then_some(())).is_none());
+}
\ No newline at end of file
@@ -0,0 +1,186 @@
+//! Sends awkward FENs, PGN and request bodies to every endpoint and checks that each one gets a
+//! JSON response with a 2xx or 4xx status. A panic anywhere in a handler fails the test.
+
+mod common;
+
+use lambda_http::http::Method;
+use serde_json::{json, Value};
+use common::send;
+
+const EDGE_CASE_FENS: &[&str] = &[
+    // Not FENs at all.
+    "",
+    "   ",
+    "not a fen",
+    "k7/8/8/8/8/8/8/K7",
+    "k7/8/8/8/8/8/8/K7 w - - 0 1 extra",
+    "k7/8/8/8/8/8/8/K7 w - - 0 1\u{00e9}",
+    // Malformed board, turn, castling and en passant fields.
+    "k8/8/8/8/8/8/8/K7 w - - 0 1",
+    "k7/9/8/8/8/8/8/K7 w - - 0 1",
+    "k7/0/8/8/8/8/8/K7 w - - 0 1",
+    "k7/8/8/8/8/8/8/8/K7 w - - 0 1",
+    "k7/8/8/8/8/8/K7 w - - 0 1",
+    "k6x/8/8/8/8/8/8/K7 w - - 0 1",
+    "k7/8/8/8/8/8/8/K7 x - - 0 1",
+    "k7/8/8/8/8/8/8/K7 w XYZ - 0 1",
+    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w HAha - 0 1",
+    "r3k2r/8/8/8/8/8/8/R3K2R w KKkq - 0 1",
+    "r3k2r/8/8/8/8/8/8/R3K2R w qkQK - 0 1",
+    "k7/8/8/8/8/8/8/K7 w - z9 0 1",
+    "k7/8/8/8/8/8/8/K7 w - e9 0 1",
+    "k7/8/8/8/8/8/8/K7 w - e3 0 1",
+    // Move counters pleco cannot parse or overflows on.
+    "k7/8/8/8/8/8/8/K7 w - - 0 0",
+    "k7/8/8/8/8/8/8/K7 w - - 0 -1",
+    "k7/8/8/8/8/8/8/K7 w - - -5 1",
+    "k7/8/8/8/8/8/8/K7 w - - 32767 1",
+    "k7/8/8/8/8/8/8/K7 w - - 99999 1",
+    "k7/8/8/8/8/8/8/K7 w - - 0 40000",
+    "k7/8/8/8/8/8/8/K7 w - - 0 4294967296",
+    // Illegal positions.
+    "8/8/8/8/8/8/8/8 w - - 0 1",
+    "8/8/8/8/8/8/8/K7 w - - 0 1",
+    "k7/8/8/8/8/8/8/K6K w - - 0 1",
+    "k6R/8/8/8/8/8/8/K7 w - - 0 1",
+    "kK6/8/8/8/8/8/8/8 b - - 0 1",
+    "k7/8/8/8/8/8/8/QQQQQQQK w - - 0 1",
+    "QQQQQQQk/QQQQQQQQ/QQQQQQQQ/QQQQQQQQ/QQQQQQQQ/QQQQQQQQ/QQQQQQQQ/QQQQQQQK w - - 0 1",
+    "kPPPPPPP/8/8/8/8/8/8/K7 w - - 0 1",
+    "k7/pppppppp/pppppppp/8/8/8/8/K7 w - - 0 1",
+    "k7/8/8/8/8/8/8/K7 w KQkq - 0 1",
+    "4k3/8/8/8/8/8/8/4K3 w K - 0 1",
+    "4k3/8/8/8/8/8/8/R3K3 w K - 0 1",
+    // Legal but unusual positions.
+    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1\n",
+    "k7/8/8/8/8/8/8/K7 w - -",
+    "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1",
+    "r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1",
+    "k7/8/8/2pP4/8/8/8/K7 w - c6 0 1",
+    "k7/8/8/8/4P3/8/8/K7 b - e3 0 1",
+    "k7/6P1/8/8/8/8/1p6/K7 w - - 0 1",
+    "k7/8/8/8/8/8/8/K7 w - - 1000 10000",
+    "k1K5/8/8/8/8/8/8/8 w - - 0 1",
+    "K6k/8/8/8/8/8/8/R6R b - - 0 1",
+    "7k/6Q1/6K1/8/8/8/8/8 b - - 0 1",
+    "7k/8/6QK/8/8/8/8/8 b - - 0 1",
+    "R6k/8/7K/8/8/8/8/8 b - - 0 1",
+    "8/8/8/8/8/8/8/K1k5 w - - 0 1",
+    // Chess960 castling rights, which standard chess rejects.
+    "1r2k1r1/8/8/8/8/8/8/1R2K1R1 w GBgb - 0 1",
+    "rk5r/8/8/8/8/8/8/RK5R w HAha - 0 1",
+    "rk5r/8/8/8/8/8/8/RK5R w KQkq - 0 1",
+];
+
+fn assert_handled(path: &str, input: &str, (status, body): &(u16, Value)) {
+    assert!(
+        (200..500).contains(status),
+        "{} with {:?} replied {}: {}", path, input, status, body
+    );
+}
+
+#[tokio::test]
+async fn edge_case_fens_never_panic() {
+    for (fen, variant) in EDGE_CASE_FENS.iter().flat_map(|fen| [(fen, "standard"), (fen, "chess960")]) {
+        let fen_body = json!({ "fen": fen, "variant": variant }).to_string();
+
+        let response = send(Method::POST, "/legal_moves", fen_body.clone()).await;
+        assert_handled("/legal_moves", fen, &response);
+
+        let response = send(Method::POST, "/validate_fen", fen_body.clone()).await;
+        assert_handled("/validate_fen", fen, &response);
+
+        let response = send(Method::POST, "/threats", fen_body).await;
+        assert_handled("/threats", fen, &response);
+
+        for chess_move in ["e2e4", "a1b1", "Kb1", "O-O", "e8=Q"] {
+            let apply_move_body = json!({ "fen": fen, "variant": variant, "move": chess_move }).to_string();
+            let response = send(Method::POST, "/apply_move", apply_move_body).await;
+            assert_handled("/apply_move", fen, &response);
+        }
+
+        let best_move_body = json!({ "fen": fen, "variant": variant, "nodes": 500 }).to_string();
+        let response = send(Method::POST, "/best_move", best_move_body).await;
+        assert_handled("/best_move", fen, &response);
+    }
+}
+
+#[tokio::test]
+async fn invalid_fens_are_rejected_with_invalid_fen() {
+    for fen in ["8/8/8/8/8/8/8/8 w - - 0 1", "k6R/8/8/8/8/8/8/K7 w - - 0 1", "k7/8/8/8/8/8/8/K7 w - - 0 0"] {
+        let (status, body) = send(Method::POST, "/legal_moves", json!({ "fen": fen })).await;
+
+        assert_eq!(status, 400, "{}", fen);
+        assert_eq!(body["code"], "INVALID_FEN", "{}", fen);
+
+        let (status, body) = send(Method::POST, "/validate_fen", json!({ "fen": fen })).await;
+
+        assert_eq!(status, 200, "{}", fen);
+        assert_eq!(body["valid"], false, "{}", fen);
+        assert_eq!(body["error"]["code"], "INVALID_FEN", "{}", fen);
+    }
+}
+
+#[tokio::test]
+async fn a_high_halfmove_clock_is_not_a_stalemate() {
+    let fen = "8/8/8/1k6/8/8/8/3KQ3 w - - 60 148";
+
+    let (status, body) = send(Method::POST, "/legal_moves", json!({ "fen": fen })).await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["game_over"], Value::Null);
+
+    let (status, body) = send(Method::POST, "/best_move", json!({ "fen": fen, "nodes": 500 })).await;
+    assert_eq!(status, 200, "{}", body);
+}
+
+#[tokio::test]
+async fn malformed_bodies_never_panic() {
+    let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+    let bodies = [
+        String::new(),
+        String::from("{"),
+        String::from("null"),
+        String::from("[]"),
+        String::from("\"fen\""),
+        json!({ "fen": 42 }).to_string(),
+        json!({ "fen": start, "move": "" }).to_string(),
+        json!({ "fen": start, "move": "e2e4", "history": ["e4", "e5", "Qh5", "Nc6", "Bc4", "Nf6", "Qxf7#"] }).to_string(),
+        json!({ "move": "\u{0000}" }).to_string(),
+        json!({ "fen": start, "multipv": 0 }).to_string(),
+        json!({ "fen": start, "multipv": usize::MAX }).to_string(),
+        json!({ "fen": start, "nodes": 0 }).to_string(),
+        json!({ "fen": start, "skill_level": 255 }).to_string(),
+        json!({ "fen": start, "elo": u32::MAX, "nodes": 500 }).to_string(),
+        json!({ "fen": start, "elo": 0, "nodes": 500 }).to_string(),
+        json!({ "fen": start, "clock": { "white_time": 0, "black_time": 0 } }).to_string(),
+        json!({ "fen": start, "clock": { "white_time": u64::MAX, "black_time": u64::MAX, "white_increment": u64::MAX, "moves_to_go": 0 }, "nodes": 500 }).to_string(),
+    ];
+
+    for body in bodies {
+        for path in ["/legal_moves", "/validate_fen", "/best_move", "/apply_move"] {
+            let response = send(Method::POST, path, body.clone()).await;
+            assert_handled(path, &body, &response);
+        }
+    }
+}
+#[tokio::test]
+async fn hostile_pgn_never_panics() {
+    let long_comment = "x".repeat(1_000_000);
+    let pgns = [
+        format!("1. e4 {}", "(1. d4 ".repeat(20_000)),
+        format!("1. e4 {}{}", "(1. d4 ".repeat(20_000), ")".repeat(20_000)),
+        format!("1. e4 {} e5", ")".repeat(20_000)),
+        "(".repeat(20_000),
+        format!("1. e4 {{{}}} e5", long_comment),
+        format!("1. e4 {{{}", long_comment),
+        format!("1. e4 ;{}", long_comment),
+        format!("[Event \"{}\"] 1. e4", long_comment),
+        format!("1. e4 {}", "$1 ".repeat(20_000)),
+    ];
+
+    for pgn in pgns {
+        let response = send(Method::POST, "/pgn/parse", json!({ "pgn": pgn })).await;
+        assert_handled("/pgn/parse", &pgn[..pgn.len().min(40)], &response);
+    }
+}
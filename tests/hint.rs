@@ -0,0 +1,64 @@
+//! Explains best moves through `POST /hint`. Each position has one move any search finds, and the
+//! tests check the reasons given for it.
+
+mod common;
+
+use lambda_http::http::Method;
+use serde_json::{json, Value};
+
+async fn hint(body: Value) -> (u16, Value) {
+    common::send(Method::POST, "/hint", body).await
+}
+
+fn reasons(body: &Value) -> Vec<&str> {
+    body["reasons"].as_array().unwrap()
+        .iter()
+        .map(|reason| reason["reason"].as_str().unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn explains_checkmate() {
+    let (status, body) = hint(json!({ "fen": "r1bqkbnr/pppp1ppp/2n5/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 2 4" })).await;
+
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["uci_move"], "h5f7");
+    assert_eq!(body["san_move"], "Qxf7#");
+    assert_eq!(body["score"], json!({ "Mate": 1 }));
+    assert_eq!(reasons(&body)[0], "Checkmate");
+    assert_eq!(body["reasons"][0]["text"], "delivers checkmate");
+}
+
+#[tokio::test]
+async fn explains_winning_material() {
+    let (status, body) = hint(json!({ "fen": "4k3/8/8/3n4/4P3/8/8/4K3 w - - 0 1", "nodes": 20000 })).await;
+
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["uci_move"], "e4d5");
+    assert_eq!(body["reasons"][0], json!({ "reason": "WinsMaterial", "see": 320, "text": "wins material (SEE +320)" }));
+}
+
+#[tokio::test]
+async fn explains_promotion() {
+    let (status, body) = hint(json!({ "fen": "8/4P1k1/8/8/8/8/8/4K3 w - - 0 1", "nodes": 20000 })).await;
+
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["uci_move"], "e7e8q");
+    assert!(reasons(&body).contains(&"Promotes"), "{}", body);
+}
+
+#[tokio::test]
+async fn rejects_positions_it_cannot_explain() {
+    let cases = [
+        (json!({ "fen": "rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3" }), "NO_LEGAL_MOVES"),
+        (json!({ "fen": "not a fen" }), "INVALID_FEN"),
+        (json!({ "fen": "4k3/8/8/8/8/8/8/4K3 w - - 0 1", "nodes": 0 }), "INVALID_REQUEST"),
+    ];
+
+    for (body, code) in cases {
+        let (status, response) = hint(body.clone()).await;
+
+        assert_eq!(status, 400, "{}: {}", body, response);
+        assert_eq!(response["code"], code, "{}", body);
+    }
+}
\ No newline at end of file
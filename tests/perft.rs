@@ -0,0 +1,144 @@
+//! Counts the standard perft positions through `POST /perft`, checking pleco's move generator
+//! against the known node counts from https://www.chessprogramming.org/Perft_Results, and
+//! Chess960 positions from https://www.chessprogramming.org/Chess960_Perft_Results.
+
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+mod common;
+
+use lambda_http::{http::Method, Context, RequestExt};
+use serde_json::{json, Value};
+use chess_rest_api_rust_pleco::router::handler;
+
+const START: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+const POSITION_3: &str = "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1";
+const POSITION_4: &str = "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1";
+const POSITION_4_MIRRORED: &str = "r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1";
+const POSITION_5: &str = "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8";
+const POSITION_6: &str = "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10";
+
+/// Chess960 positions with their castling rights in Shredder-FEN.
+const CHESS960_POSITIONS: [(&str, [u64; 4]); 5] = [
+    ("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9", [21, 528, 12_189, 326_672]),
+    ("2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w HEhe - 1 9", [21, 807, 18_002, 667_366]),
+    ("b1q1rrkb/pppppppp/3nn3/8/P7/1PPP4/4PPPP/BQNNRKRB w GE - 1 9", [20, 479, 10_471, 273_318]),
+    ("qbbnnrkr/2pp2pp/p7/1p2pp2/8/P3PP2/1PPP1KPP/QBBNNR1R w hf - 0 9", [22, 593, 13_440, 382_958]),
+    ("1nbbnrkr/p1p1ppp1/3p4/1p3P1p/3Pq2P/8/PPP1P1P1/QNBBNRKR w HFhf - 0 9", [28, 1_120, 31_058, 1_171_749]),
+];
+
+async fn perft(variant: &str, fen: &str, depth: u8) -> (u16, Value) {
+    common::send(Method::POST, "/perft", json!({ "fen": fen, "depth": depth, "variant": variant })).await
+}
+
+/// Checks the count at every depth up to the last given.
+async fn assert_counts(variant: &str, fen: &str, counts: &[u64]) {
+    for (depth, &nodes) in (1..).zip(counts) {
+        let (status, body) = perft(variant, fen, depth).await;
+
+        assert_eq!(status, 200, "{} depth {}: {}", fen, depth, body);
+        assert_eq!(body["nodes"], nodes, "{} depth {}", fen, depth);
+
+        let divided: u64 = body["divide"].as_array().unwrap().iter().map(|mv| mv["nodes"].as_u64().unwrap()).sum();
+        assert_eq!(divided, nodes, "{} depth {}", fen, depth);
+    }
+}
+
+#[tokio::test]
+async fn start_position() {
+    assert_counts("standard", START, &[20, 400, 8_902, 197_281, 4_865_609]).await;
+}
+
+#[tokio::test]
+async fn kiwipete() {
+    assert_counts("standard", KIWIPETE, &[48, 2_039, 97_862, 4_085_603]).await;
+}
+
+#[tokio::test]
+async fn position_3() {
+    assert_counts("standard", POSITION_3, &[14, 191, 2_812, 43_238, 674_624]).await;
+}
+
+#[tokio::test]
+async fn position_4() {
+    assert_counts("standard", POSITION_4, &[6, 264, 9_467, 422_333]).await;
+    assert_counts("standard", POSITION_4_MIRRORED, &[6, 264, 9_467, 422_333]).await;
+}
+
+#[tokio::test]
+async fn position_5() {
+    assert_counts("standard", POSITION_5, &[44, 1_486, 62_379, 2_103_487]).await;
+}
+
+#[tokio::test]
+async fn position_6() {
+    assert_counts("standard", POSITION_6, &[46, 2_079, 89_890, 3_894_594]).await;
+}
+
+#[tokio::test]
+async fn divides_by_move() {
+    let (status, body) = perft("standard", KIWIPETE, 2).await;
+
+    assert_eq!(status, 200, "{}", body);
+
+    let divide = body["divide"].as_array().unwrap();
+    assert_eq!(divide.len(), 48);
+    assert!(divide.windows(2).all(|pair| pair[0]["uci_move"].as_str() < pair[1]["uci_move"].as_str()));
+    assert!(divide.contains(&json!({ "uci_move": "e1g1", "san_move": "O-O", "nodes": 43 })), "{}", body);
+    assert!(divide.contains(&json!({ "uci_move": "d5e6", "san_move": "dxe6", "nodes": 46 })), "{}", body);
+}
+
+#[tokio::test]
+async fn chess960_positions() {
+    for (fen, counts) in CHESS960_POSITIONS {
+        assert_counts("chess960", fen, &counts).await;
+    }
+}
+
+#[tokio::test]
+async fn writes_chess960_castling_as_the_king_taking_its_rook() {
+    let (status, body) = perft("chess960", "1r2k1r1/8/8/8/8/8/8/1R2K1R1 w GBgb - 0 1", 1).await;
+
+    assert_eq!(status, 200, "{}", body);
+    assert!(body["divide"].as_array().unwrap().contains(&json!({ "uci_move": "e1b1", "san_move": "O-O-O", "nodes": 1 })), "{}", body);
+
+    // The same castling rights cannot be given in standard chess.
+    let (status, body) = perft("standard", "1r2k1r1/8/8/8/8/8/8/1R2K1R1 w GBgb - 0 1", 1).await;
+    assert_eq!(status, 400, "{}", body);
+    assert_eq!(body["code"], "INVALID_FEN");
+}
+
+#[tokio::test]
+async fn counts_nothing_after_the_game_ends() {
+    let (status, body) = perft("standard", "rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3", 3).await;
+
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body, json!({ "nodes": 0, "divide": [] }));
+}
+
+#[tokio::test]
+async fn rejects_depths_out_of_range() {
+    for depth in [0, 7] {
+        let (status, body) = perft("standard", START, depth).await;
+
+        assert_eq!(status, 400, "depth {}: {}", depth, body);
+        assert_eq!(body["details"], json!({ "field": "depth" }));
+    }
+}
+
+#[tokio::test]
+async fn stops_counting_before_the_deadline() {
+    // Leaves about 100ms to count once the safety margin is kept back, far too little for depth 6.
+    let deadline = SystemTime::now() + Duration::from_millis(600);
+    let mut context = Context::default();
+    context.deadline = deadline.duration_since(UNIX_EPOCH).unwrap().as_millis() as u64;
+
+    let request = common::request(Method::POST, "/perft", json!({ "fen": KIWIPETE, "depth": 6 }))
+        .with_lambda_context(context);
+
+    let start = Instant::now();
+    let (status, body) = common::read(handler(request).await.unwrap());
+
+    assert_eq!(status, 503, "{}", body);
+    assert_eq!(body["code"], "TIMEOUT");
+    assert!(start.elapsed() < Duration::from_millis(600), "responded after {:?}", start.elapsed());
+}
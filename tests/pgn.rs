@@ -0,0 +1,169 @@
+//! Parses PGN through `POST /pgn/parse` and checks the positions and error locations it reports,
+//! and writes games through `POST /pgn/export`.
+
+mod common;
+
+use lambda_http::http::Method;
+use serde_json::{json, Value};
+
+async fn parse(pgn: &str) -> (u16, Value) {
+    common::send(Method::POST, "/pgn/parse", json!({ "pgn": pgn })).await
+}
+
+async fn export(body: Value) -> (u16, Value) {
+    common::send(Method::POST, "/pgn/export", body).await
+}
+
+#[tokio::test]
+async fn parses_tags_comments_nags_and_variations() {
+    let pgn = "[Event \"Casual\"]\n[Site \"?\"]\n\n\
+        {Start} 1. e4 e5 2. Nf3!? (2. f4 exf4 {The gambit} (2... d5)) 2... Nc6 $14 3. Bb5 a6 1-0";
+    let (status, body) = parse(pgn).await;
+
+    assert_eq!(status, 200, "{}", body);
+    let game = &body["games"][0];
+    assert_eq!(game["tags"][0], json!({ "name": "Event", "value": "Casual" }));
+    assert_eq!(game["comments"], json!(["Start"]));
+    assert_eq!(game["result"], "1-0");
+
+    let moves = game["moves"].as_array().unwrap();
+    assert_eq!(moves.len(), 6);
+    assert_eq!(moves[2]["uci_move"], "g1f3");
+    assert_eq!(moves[2]["nags"], json!([5]));
+    assert_eq!(moves[3]["nags"], json!([14]));
+    assert_eq!(moves[5]["resulting_fen"], "r1bqkbnr/1ppp1ppp/p1n5/1B2p3/4P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 0 4");
+
+    let variation = moves[2]["variations"][0].as_array().unwrap();
+    assert_eq!(variation[0]["ply"], 3);
+    assert_eq!(variation[0]["san_move"], "f4");
+    assert_eq!(variation[1]["comments"], json!(["The gambit"]));
+    assert_eq!(variation[1]["variations"][0][0]["uci_move"], "d7d5");
+}
+
+#[tokio::test]
+async fn starts_from_the_fen_tag() {
+    let pgn = "[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/8/4K2R w K - 0 1\"]\n\n1. O-O Kd7 *\n\n1. d4 d5 *";
+    let (status, body) = parse(pgn).await;
+
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["games"][0]["moves"][0]["uci_move"], "e1g1");
+    assert_eq!(body["games"][0]["result"], "*");
+    assert_eq!(body["games"][1]["moves"][0]["san_move"], "d4");
+}
+
+#[tokio::test]
+async fn errors_point_at_the_offending_move() {
+    let (status, body) = parse("1. e4 e5\n2. Ke3 Nc6").await;
+
+    assert_eq!(status, 400);
+    assert_eq!(body["code"], "ILLEGAL_MOVE");
+    assert_eq!(body["details"], json!({ "game": 0, "line": 2, "column": 4, "offset": 12, "move": "Ke3" }));
+
+    let (status, body) = parse("1. e4 e5 *\n\n1. d4 Zz9").await;
+
+    assert_eq!(status, 400);
+    assert_eq!(body["code"], "INVALID_PGN");
+    assert_eq!(body["details"], json!({ "game": 1, "line": 3, "column": 7, "offset": 18, "move": "Zz9" }));
+}
+
+#[tokio::test]
+async fn malformed_pgn_is_rejected() {
+    for pgn in ["", "1. e4 {never closed", "1. e4 (1. d4", "1. e4 ) e5", "( 1. e4", "$1 e4", "[Event \"x", "1. e4 (1. d4 1-0)"] {
+        let (status, body) = parse(pgn).await;
+
+        assert_eq!(status, 400, "{:?}: {}", pgn, body);
+        assert_eq!(body["code"], "INVALID_PGN", "{:?}", pgn);
+    }
+}
+
+#[tokio::test]
+async fn deeply_nested_variations_are_rejected() {
+    // Each variation replaces the first move of the one around it.
+    let nested = |depth| format!("e4 {}{}", "(d4 ".repeat(depth), ")".repeat(depth));
+
+    let (status, body) = parse(&nested(32)).await;
+    assert_eq!(status, 200, "{}", body);
+
+    let (status, body) = parse(&nested(20_000)).await;
+    assert_eq!(status, 400);
+    assert_eq!(body["code"], "INVALID_PGN");
+    assert_eq!(body["message"], "Variations are nested more than 32 deep.");
+    assert_eq!(body["details"], json!({ "game": 0, "line": 1, "column": 132, "offset": 131 }));
+}
+
+#[tokio::test]
+async fn exported_games_parse_back_to_the_same_moves() {
+    let moves = ["e4", "e5", "Qh5", "Nc6", "Bc4", "Nf6", "Qxf7#"];
+    let (status, body) = export(json!({
+        "moves": moves,
+        "tags": [{ "name": "White", "value": "A \"quoted\" name" }, { "name": "Result", "value": "0-1" }],
+        "annotations": [null, { "comment": "Open game", "eval": { "Centipawns": -30 } }],
+    })).await;
+
+    assert_eq!(status, 200, "{}", body);
+    let pgn = body["pgn"].as_str().unwrap();
+    assert!(pgn.starts_with("[Event \"?\"]\n[Site \"?\"]\n[Date \"????.??.??\"]\n[Round \"?\"]\n"), "{}", pgn);
+    assert!(pgn.contains("[White \"A \\\"quoted\\\" name\"]\n"), "{}", pgn);
+    // The game ended in checkmate, so the given result is replaced.
+    assert!(pgn.contains("[Result \"1-0\"]\n"), "{}", pgn);
+    assert!(pgn.ends_with("1. e4 e5 {[%eval 0.30] Open game} 2. Qh5 Nc6 3. Bc4 Nf6 4. Qxf7# 1-0\n"), "{}", pgn);
+
+    let (status, body) = parse(pgn).await;
+
+    assert_eq!(status, 200, "{}", body);
+    let game = &body["games"][0];
+    assert_eq!(game["tags"][4]["value"], "A \"quoted\" name");
+    assert_eq!(game["result"], "1-0");
+    let parsed: Vec<&str> = game["moves"].as_array().unwrap().iter().map(|ply| ply["uci_move"].as_str().unwrap()).collect();
+    assert_eq!(parsed, ["e2e4", "e7e5", "d1h5", "b8c6", "f1c4", "g8f6", "h5f7"]);
+}
+
+#[tokio::test]
+async fn exports_set_up_positions_with_their_fen() {
+    let fen = "4k3/8/8/8/8/8/8/4K2R b K - 0 10";
+    let (status, body) = export(json!({ "fen": fen, "moves": ["e8d7", "e1g1"] })).await;
+
+    assert_eq!(status, 200, "{}", body);
+    let pgn = body["pgn"].as_str().unwrap();
+    assert!(pgn.contains("[Result \"*\"]\n[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/8/4K2R b K - 0 10\"]\n\n10... Kd7 11. O-O *\n"), "{}", pgn);
+
+    let (status, body) = export(json!({ "fen": fen, "moves": ["e8d7", "e8d8"] })).await;
+
+    assert_eq!(status, 400);
+    assert_eq!(body["code"], "ILLEGAL_MOVE");
+    assert_eq!(body["details"]["move_index"], 1);
+}
+
+#[tokio::test]
+async fn chess960_games_round_trip_with_their_variant() {
+    let fen = "1r2k1r1/8/8/8/8/8/8/1R2K1R1 w GBgb - 0 1";
+    let (status, body) = export(json!({ "fen": fen, "variant": "chess960", "moves": ["e1b1", "Kf8"] })).await;
+
+    assert_eq!(status, 200, "{}", body);
+    let pgn = body["pgn"].as_str().unwrap();
+    assert!(pgn.contains("[Variant \"Chess960\"]\n[SetUp \"1\"]\n[FEN \"1r2k1r1/8/8/8/8/8/8/1R2K1R1 w KQkq - 0 1\"]\n\n1. O-O-O Kf8 *\n"), "{}", pgn);
+
+    let (status, body) = parse(pgn).await;
+
+    assert_eq!(status, 200, "{}", body);
+    let game = &body["games"][0];
+    assert_eq!(game["variant"], "chess960");
+    assert_eq!(game["moves"][0]["uci_move"], "e1b1");
+    assert_eq!(game["moves"][0]["resulting_fen"], "1r2k1r1/8/8/8/8/8/8/2KR2R1 b kq - 1 1");
+
+    // Without the Variant tag the castling rights are not those of standard chess.
+    let (status, body) = parse(&pgn.replace("[Variant \"Chess960\"]\n", "")).await;
+    assert_eq!(status, 400, "{}", body);
+    assert_eq!(body["code"], "INVALID_FEN");
+}
+
+#[tokio::test]
+async fn long_games_are_wrapped() {
+    let moves: Vec<&str> = ["Nf3", "Nf6", "Ng1", "Ng8"].iter().cycle().take(40).copied().collect();
+    let (status, body) = export(json!({ "moves": moves })).await;
+
+    assert_eq!(status, 200, "{}", body);
+    let pgn = body["pgn"].as_str().unwrap();
+    assert!(pgn.lines().all(|line| line.len() <= 79), "{}", pgn);
+    assert!(pgn.lines().count() > 10, "{}", pgn);
+}
\ No newline at end of file
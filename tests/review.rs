@@ -0,0 +1,72 @@
+//! Reviews short games through `POST /review`. The searches are timed, so the positions are chosen
+//! to have a best move any search finds.
+
+mod common;
+
+use lambda_http::http::Method;
+use serde_json::{json, Value};
+
+async fn review(body: Value) -> (u16, Value) {
+    common::send(Method::POST, "/review", body).await
+}
+
+#[tokio::test]
+async fn finds_the_blunder_that_allows_mate() {
+    let (status, body) = review(json!({
+        "fen": "r1bqkbnr/pppp1ppp/2n5/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR b KQkq - 3 3",
+        "moves": ["Nf6", "Qxf7#"],
+        "time_budget_ms": 1000,
+    })).await;
+
+    assert_eq!(status, 200, "{}", body);
+
+    let blunder = &body["moves"][0];
+    assert_eq!(blunder["colour"], "Black");
+    assert_eq!(blunder["move_number"], 3);
+    assert_eq!(blunder["uci_move"], "g8f6");
+    assert_eq!(blunder["eval"], json!({ "Mate": -1 }));
+    assert_ne!(blunder["best_uci_move"], "g8f6");
+    assert_eq!(blunder["classification"], "Blunder");
+
+    let mate = &body["moves"][1];
+    assert_eq!(mate["eval"], json!({ "Mate": 1 }));
+    assert_eq!(mate["centipawn_loss"], 0);
+    assert_eq!(mate["classification"], "Best");
+
+    assert_eq!(body["white"]["accuracy"], 100.0);
+    assert_eq!(body["black"]["blunders"], 1);
+}
+
+#[tokio::test]
+async fn reviews_the_main_line_of_a_pgn() {
+    let (status, body) = review(json!({
+        "pgn": "[SetUp \"1\"]\n[FEN \"6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - 0 1\"]\n\n1. Ra8# (1. h3) 1-0",
+        "time_budget_ms": 200,
+    })).await;
+
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["starting_fen"], "6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - 0 1");
+    assert_eq!(body["moves"].as_array().unwrap().len(), 1);
+    assert_eq!(body["moves"][0]["classification"], "Best");
+    assert_eq!(body["black"]["moves"], 0);
+    assert_eq!(body["black"]["accuracy"], Value::Null);
+}
+
+#[tokio::test]
+async fn rejects_games_it_cannot_review() {
+    let cases = [
+        (json!({}), "INVALID_REQUEST"),
+        (json!({ "pgn": "1. e4 *", "moves": ["e4"] }), "INVALID_REQUEST"),
+        (json!({ "pgn": "1. e4 * 1. d4 *" }), "INVALID_REQUEST"),
+        (json!({ "moves": ["e4"], "time_budget_ms": 0 }), "INVALID_REQUEST"),
+        (json!({ "moves": ["e4", "e4"] }), "ILLEGAL_MOVE"),
+        (json!({ "pgn": "1. e4 e4" }), "ILLEGAL_MOVE"),
+    ];
+
+    for (body, code) in cases {
+        let (status, response) = review(body.clone()).await;
+
+        assert_eq!(status, 400, "{}: {}", body, response);
+        assert_eq!(response["code"], code, "{}", body);
+    }
+}
\ No newline at end of file
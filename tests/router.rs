@@ -0,0 +1,85 @@
+//! Checks how requests are routed to endpoints, whatever the endpoint does with them.
+
+mod common;
+
+use lambda_http::http::{header, Method};
+use serde_json::json;
+use chess_rest_api_rust_pleco::router::handler;
+
+#[tokio::test]
+async fn ignores_the_body_of_a_get() {
+    let (status, body) = common::send(Method::GET, "/health_check", json!({ "a": 1 })).await;
+    assert_eq!(status, 200, "{}", body);
+
+    let (status, body) = common::send(Method::GET, "/health_check", "not json").await;
+    assert_eq!(status, 200, "{}", body);
+}
+
+#[tokio::test]
+async fn a_known_path_with_the_wrong_method_is_not_allowed() {
+    let response = handler(common::request(Method::GET, "/best_move", "")).await.unwrap();
+    assert_eq!(response.headers()[header::ALLOW], "POST, OPTIONS");
+
+    let (status, body) = common::read(response);
+    assert_eq!(status, 405, "{}", body);
+    assert_eq!(body["code"], "METHOD_NOT_ALLOWED");
+    assert_eq!(body["details"]["allow"], json!(["POST", "OPTIONS"]));
+}
+
+#[tokio::test]
+async fn an_unknown_path_is_not_found() {
+    let (status, body) = common::send(Method::POST, "/no_such_route", json!({})).await;
+
+    assert_eq!(status, 404, "{}", body);
+    assert_eq!(body["code"], "NOT_FOUND");
+    assert_eq!(body["message"], "No route for /no_such_route.");
+}
+
+#[tokio::test]
+async fn a_body_that_is_not_json_is_invalid_json() {
+    let (status, body) = common::send(Method::POST, "/legal_moves", "{\"fen\": ").await;
+
+    assert_eq!(status, 400, "{}", body);
+    assert_eq!(body["code"], "INVALID_JSON");
+    assert_eq!(body["details"], json!({ "line": 1, "column": 8 }));
+}
+
+#[tokio::test]
+async fn json_of_the_wrong_shape_is_an_invalid_request() {
+    for request_body in [json!({ "fen": 42 }), json!({}), json!([])] {
+        let (status, body) = common::send(Method::POST, "/legal_moves", &request_body).await;
+
+        assert_eq!(status, 400, "{}: {}", request_body, body);
+        assert_eq!(body["code"], "INVALID_REQUEST", "{}", request_body);
+    }
+}
+
+#[tokio::test]
+async fn every_response_is_json() {
+    for request in [
+        common::request(Method::GET, "/health_check", ""),
+        common::request(Method::POST, "/legal_moves", "not json"),
+        common::request(Method::POST, "/no_such_route", ""),
+    ] {
+        let response = handler(request).await.unwrap();
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/json");
+    }
+}
+
+#[tokio::test]
+async fn answers_a_preflight_for_any_origin_by_default() {
+    let mut request = common::request(Method::OPTIONS, "/best_move", "");
+    request.headers_mut().insert(header::ORIGIN, "https://example.com".parse().unwrap());
+    request.headers_mut().insert(header::ACCESS_CONTROL_REQUEST_METHOD, "POST".parse().unwrap());
+
+    let response = handler(request).await.unwrap();
+    let headers = response.headers();
+
+    assert_eq!(response.status().as_u16(), 204);
+    assert!(response.body().is_empty());
+    assert_eq!(headers[header::ALLOW], "POST, OPTIONS");
+    assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+    assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_METHODS], "POST, OPTIONS");
+    assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_HEADERS], "Content-Type");
+    assert_eq!(headers[header::ACCESS_CONTROL_MAX_AGE], "86400");
+}
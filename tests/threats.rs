@@ -0,0 +1,69 @@
+//! Finds the threats in positions through `POST /threats`.
+
+mod common;
+
+use lambda_http::http::Method;
+use serde_json::{json, Value};
+
+async fn threats(fen: &str) -> (u16, Value) {
+    common::send(Method::POST, "/threats", json!({ "fen": fen })).await
+}
+
+#[tokio::test]
+async fn finds_threats_for_both_sides() {
+    let (status, body) = threats("4k3/8/3q4/8/4N3/8/3p4/4R1K1 w - - 0 1").await;
+
+    assert_eq!(status, 200, "{}", body);
+
+    let white = &body["side_to_move"];
+    assert_eq!(white["colour"], "White");
+    assert_eq!(white["hanging"], json!([{ "piece": "Rook", "square": "e1", "value": 500 }]));
+    assert_eq!(white["attacked_by_lower"], json!([
+        { "piece": "Rook", "square": "e1", "attackers": [{ "piece": "Pawn", "square": "d2" }] },
+    ]));
+    assert_eq!(white["discovered_attacks"], json!([{
+        "piece": "Knight",
+        "square": "e4",
+        "attacker": { "piece": "Rook", "square": "e1" },
+        "target": { "piece": "King", "square": "e8" },
+    }]));
+
+    let black = &body["opponent"];
+    assert_eq!(black["colour"], "Black");
+    assert_eq!(black["attacked_by_lower"][0]["piece"], "Queen");
+    assert_eq!(black["forks"][1], json!({
+        "uci_move": "d2e1q",
+        "san_move": "dxe1=Q+",
+        "piece": "Queen",
+        "targets": [{ "piece": "King", "square": "g1" }, { "piece": "Knight", "square": "e4" }],
+    }));
+}
+
+#[tokio::test]
+async fn finds_pins() {
+    let (status, body) = threats("r3k2r/pp3ppp/2n5/1B1N4/8/8/PPP2PPP/R3K2R b KQkq - 0 1").await;
+
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["side_to_move"]["pinned"], json!([
+        { "piece": "Knight", "square": "c6", "pinned_by": { "piece": "Bishop", "square": "b5" } },
+    ]));
+    assert_eq!(body["opponent"]["forks"][0]["san_move"], "Nc7+");
+}
+
+#[tokio::test]
+async fn only_the_side_to_move_has_tactics_in_check() {
+    let (status, body) = threats("4k3/8/8/8/8/8/4q3/4K3 w - - 0 1").await;
+
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["side_to_move"]["attacked_by_lower"], json!([]));
+    assert_eq!(body["opponent"]["hanging"][0]["square"], "e2");
+    assert_eq!(body["opponent"]["forks"], json!([]));
+}
+
+#[tokio::test]
+async fn rejects_invalid_fens() {
+    let (status, body) = threats("not a fen").await;
+
+    assert_eq!(status, 400);
+    assert_eq!(body["code"], "INVALID_FEN");
+}
\ No newline at end of file